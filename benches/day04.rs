@@ -0,0 +1,53 @@
+use aoc_2025::day04::{BitGrid, Rules, Tile, removable_rolls, removable_rolls_rescan};
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use ndarray::Array2;
+use rand::{SeedableRng, rngs::StdRng};
+
+#[path = "../src/day04/random.rs"]
+mod random;
+
+fn warehouse(size: usize) -> Array2<Tile> {
+    random::random_warehouse(&mut StdRng::seed_from_u64(2025), (size, size), 0.7)
+}
+
+fn part2(c: &mut Criterion) {
+    let grid = warehouse(2000);
+    let rules = Rules::default();
+    assert_eq!(
+        removable_rolls(&grid, &rules),
+        removable_rolls_rescan(&grid, &rules)
+    );
+
+    let mut group = c.benchmark_group("day04 part2 2000x2000");
+    group.sample_size(10);
+    group.bench_function("worklist", |b| b.iter(|| removable_rolls(&grid, &rules)));
+    group.bench_function("rescan", |b| {
+        b.iter(|| removable_rolls_rescan(&grid, &rules))
+    });
+    group.finish();
+}
+
+fn bit_grid(c: &mut Criterion) {
+    let grid = warehouse(10000);
+    let bits = BitGrid::from_tiles(&grid);
+    let rules = Rules::default();
+    assert_eq!(
+        bits.clone().remove_accessible(),
+        removable_rolls(&grid, &rules)
+    );
+
+    let mut group = c.benchmark_group("day04 bit grid 10000x10000");
+    group.sample_size(10);
+    group.bench_function("part1", |b| b.iter(|| bits.accessible_count()));
+    group.bench_function("part2", |b| {
+        b.iter_batched(
+            || bits.clone(),
+            |mut bits| bits.remove_accessible(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, part2, bit_grid);
+criterion_main!(benches);
@@ -1,13 +1,17 @@
+use std::fmt::{self, Write};
+
 use itertools::Itertools;
 
+use crate::input::{self, InputLine, ParseError};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
+pub enum Direction {
     Left,
     Right,
 }
 
 impl Direction {
-    fn apply(self, num: i32) -> i32 {
+    fn apply(self, num: i64) -> i64 {
         match self {
             Self::Left => -num,
             Self::Right => num,
@@ -15,70 +19,340 @@ impl Direction {
     }
 }
 
-#[aoc_generator(day01)]
-fn parse(input: &str) -> Vec<(Direction, i32)> {
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char(match self {
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        })
+    }
+}
+
+/// What happened during a single rotation of a `Dial`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RotationReport {
+    /// Where the dial stopped
+    pub position: u32,
+    /// How many clicks landed on 0, including the last one
+    pub zeroes: u64,
+}
+
+/// A dial with positions `0..size`, turning right goes up and wraps around back to 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Dial {
+    size: u32,
+    start: u32,
+    position: u32,
+}
+
+impl Dial {
+    pub fn new(size: u32, start: u32) -> Self {
+        assert!(size > 0, "Dial must have at least one position");
+        assert!(
+            start < size,
+            "Start {} is not on a dial of size {}",
+            start,
+            size
+        );
+
+        Self {
+            size,
+            start,
+            position: start,
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    pub fn reset(&mut self) {
+        self.position = self.start;
+    }
+
+    pub fn rotate(&mut self, dir: Direction, amount: u32) -> RotationReport {
+        let report = step(self.size, self.position, dir, amount);
+        self.position = report.position;
+        report
+    }
+}
+
+/// Turns a dial of `size` positions by `amount` clicks from `position`
+///
+/// Both the landing position and the zero count come from the same `rem_euclid`: the distance
+/// already covered from 0 in the direction of the rotation, which is `position` going right and
+/// `size - position` (or 0) going left
+fn step(size: u32, position: u32, dir: Direction, amount: u32) -> RotationReport {
+    let size = i64::from(size);
+    let distance = dir.apply(position.into()).rem_euclid(size);
+    let covered = distance + i64::from(amount);
+
+    RotationReport {
+        position: dir.apply(covered.rem_euclid(size)).rem_euclid(size) as u32,
+        zeroes: (covered / size) as u64,
+    }
+}
+
+impl Default for Dial {
+    fn default() -> Self {
+        Self::new(100, 50)
+    }
+}
+
+/// One instruction of a trace, along with where the dial was before and after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceStep {
+    pub direction: Direction,
+    pub amount: u32,
+    pub before: u32,
+    pub after: u32,
+    /// How many clicks of this instruction landed on 0
+    pub zeroes: u64,
+}
+
+/// Follows every instruction of `input` on the puzzle's dial
+pub fn trace(input: &[(Direction, u32)]) -> Vec<TraceStep> {
+    let mut dial = Dial::default();
     input
-        .lines()
-        .map(str::trim)
-        .filter(|line| !line.is_empty())
-        .map(|l| {
-            if let Some(num) = l.strip_prefix('L') {
-                return (Direction::Left, num.parse().expect("Failed to parse num"));
+        .iter()
+        .map(|&(direction, amount)| {
+            let before = dial.position();
+            let report = dial.rotate(direction, amount);
+            TraceStep {
+                direction,
+                amount,
+                before,
+                after: report.position,
+                zeroes: report.zeroes,
             }
+        })
+        .collect()
+}
 
-            if let Some(num) = l.strip_prefix('R') {
-                return (Direction::Right, num.parse().expect("Failed to parse num"));
-            }
+const TRACE_HEADER: [&str; 4] = ["instruction", "before", "after", "zeroes"];
 
-            panic!("Line {:?}", l);
-        })
-        .collect_vec()
+fn trace_row(step: &TraceStep) -> [String; 4] {
+    [
+        format!("{}{}", step.direction, step.amount),
+        step.before.to_string(),
+        step.after.to_string(),
+        step.zeroes.to_string(),
+    ]
 }
 
-#[aoc(day01, part1)]
-fn part1(input: &[(Direction, i32)]) -> usize {
-    let mut count = 0;
-    let mut pos = 50;
-
-    for &(dir, num) in input {
-        pos += dir.apply(num);
-        pos %= 100;
-        if pos == 0 {
-            count += 1;
+pub fn render_csv(steps: &[TraceStep]) -> String {
+    let mut out = TRACE_HEADER.join(",");
+    out.push('\n');
+
+    for step in steps {
+        out.push_str(&trace_row(step).join(","));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Renders the steps as a markdown-like table, the instructions are left aligned and the numbers
+/// right aligned
+pub fn render_table(steps: &[TraceStep]) -> String {
+    let rows = steps.iter().map(trace_row).collect_vec();
+    let widths: [usize; 4] = std::array::from_fn(|col| {
+        rows.iter()
+            .map(|row| row[col].len())
+            .chain([TRACE_HEADER[col].len()])
+            .max()
+            .unwrap_or_default()
+    });
+
+    let mut out = String::new();
+    let header = TRACE_HEADER.map(str::to_owned);
+    let separator = widths.map(|width| "-".repeat(width));
+
+    for (i, row) in [header, separator].iter().chain(&rows).enumerate() {
+        for (col, cell) in row.iter().enumerate() {
+            let width = widths[col];
+            if col == 0 || i < 2 {
+                write!(out, "| {:<width$} ", cell).expect("Writing to a String cannot fail");
+            } else {
+                write!(out, "| {:>width$} ", cell).expect("Writing to a String cannot fail");
+            }
         }
+        out.push_str("|\n");
     }
 
-    count
+    out
+}
+
+fn parse_rotation(line: InputLine, text: &str) -> Result<(Direction, u32), ParseError> {
+    let split = text.chars().next().map_or(0, char::len_utf8);
+    let (direction, num) = text.split_at(split);
+    let direction = match direction {
+        "L" => Direction::Left,
+        "R" => Direction::Right,
+        _ => return Err(line.unexpected(direction, "'L' or 'R'")),
+    };
+
+    Ok((direction, line.parse(num)?))
+}
+
+#[aoc_generator(day01)]
+fn parse(input: &str) -> Result<Vec<(Direction, u32)>, ParseError> {
+    input::lines(1, input)
+        .map(|line| parse_rotation(line, line.text))
+        .try_collect()
+}
+
+#[aoc(day01, part1)]
+fn part1(input: &[(Direction, u32)]) -> usize {
+    let mut dial = Dial::default();
+    input
+        .iter()
+        .filter(|&&(dir, num)| dial.rotate(dir, num).position == 0)
+        .count()
 }
 
 #[aoc(day01, part2)]
-fn part2(input: &[(Direction, i32)]) -> usize {
-    let mut count = 0;
-    let mut pos = 50;
-
-    for &(dir, num) in input {
-        let clamped_num = num % 100;
-        count += num as usize / 100;
-        let is_zero = pos == 0;
-        pos += dir.apply(clamped_num);
-        if pos < 0 {
-            count += if is_zero { 0 } else { 1 };
-            pos += 100;
-        } else if pos >= 100 {
-            count += if is_zero { 0 } else { 1 };
-            pos -= 100;
-        } else if pos == 0 {
-            count += 1;
+fn part2(input: &[(Direction, u32)]) -> u64 {
+    let mut dial = Dial::default();
+    input
+        .iter()
+        .map(|&(dir, num)| dial.rotate(dir, num).zeroes)
+        .sum()
+}
+
+/// A rotation of a single dial of a `DialStack`, written `2:R48`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StackRotation {
+    pub dial: usize,
+    pub direction: Direction,
+    pub amount: u32,
+}
+
+/// Parses rotations for a stack of `dials` dials
+pub fn parse_stack(input: &str, dials: usize) -> Result<Vec<StackRotation>, ParseError> {
+    input::lines(1, input)
+        .map(|line| {
+            let Some((dial, rotation)) = line.text.split_once(':') else {
+                return Err(line.unexpected(line.text, "'<dial>:<rotation>'"));
+            };
+
+            let index: usize = line.parse(dial)?;
+            if index >= dials {
+                return Err(line.invalid(dial, format!("the stack has {} dials", dials)));
+            }
+
+            let (direction, amount) = parse_rotation(line, rotation)?;
+            Ok(StackRotation {
+                dial: index,
+                direction,
+                amount,
+            })
+        })
+        .try_collect()
+}
+
+/// Dials linked like an odometer: every time a dial wraps from its last position to 0 the next
+/// one turns right by a click, and every time it wraps from 0 to its last position the next one
+/// turns left by a click
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialStack {
+    dials: Vec<Dial>,
+    zeroes: Vec<u64>,
+    overflows: u64,
+}
+
+impl DialStack {
+    pub fn new(dials: impl IntoIterator<Item = Dial>) -> Self {
+        let dials = dials.into_iter().collect_vec();
+        Self {
+            zeroes: vec![0; dials.len()],
+            dials,
+            overflows: 0,
         }
     }
 
-    count
+    pub fn dials(&self) -> &[Dial] {
+        &self.dials
+    }
+
+    /// How many clicks landed on 0, for every dial
+    pub fn zeroes(&self) -> &[u64] {
+        &self.zeroes
+    }
+
+    /// How many times the last dial wrapped around
+    pub fn overflows(&self) -> u64 {
+        self.overflows
+    }
+
+    pub fn rotate(&mut self, rotation: StackRotation) {
+        let StackRotation {
+            mut dial,
+            direction,
+            mut amount,
+        } = rotation;
+
+        while amount > 0 {
+            let current = &mut self.dials[dial];
+
+            // Wrapping right lands on 0, wrapping left lands on the last position which is where
+            // 0 would be if the dial was one click further
+            let wraps = match direction {
+                Direction::Left => {
+                    let shifted = (current.position() + 1) % current.size();
+                    step(current.size(), shifted, direction, amount).zeroes
+                }
+                Direction::Right => {
+                    step(current.size(), current.position(), direction, amount).zeroes
+                }
+            };
+            self.zeroes[dial] += current.rotate(direction, amount).zeroes;
+
+            dial += 1;
+            if dial == self.dials.len() {
+                self.overflows += wraps;
+                break;
+            }
+
+            amount = u32::try_from(wraps).expect("A dial cannot wrap more than u32::MAX times");
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
     use super::*;
 
+    /// Turns the dial one click at a time, returns the zeroes landed on at the end of a rotation
+    /// and the zeroes landed on by any click
+    fn naive(size: u32, start: u32, input: &[(Direction, u32)]) -> (usize, u64) {
+        let (mut landed, mut clicked) = (0, 0);
+        let mut pos = start;
+
+        for &(dir, num) in input {
+            for _ in 0..num {
+                pos = match dir {
+                    Direction::Left => pos.checked_sub(1).unwrap_or(size - 1),
+                    Direction::Right => (pos + 1) % size,
+                };
+                clicked += (pos == 0) as u64;
+            }
+            landed += (pos == 0) as usize;
+        }
+
+        (landed, clicked)
+    }
+
     const EXAMPLE: &str = "
             L68
             L30
@@ -94,11 +368,209 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), 3);
+        assert_eq!(part1(&parse(EXAMPLE).expect("Could not parse")), 3);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), 6);
+        assert_eq!(part2(&parse(EXAMPLE).expect("Could not parse")), 6);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse("L68\n  X30").expect_err("Parsed an unknown direction");
+        assert_eq!(
+            err.to_string(),
+            "day 1, line 2, column 3: unexpected \"X\", expected 'L' or 'R'"
+        );
+
+        let err = parse("L68\nR3O").expect_err("Parsed a letter");
+        assert_eq!(
+            err.to_string(),
+            "day 1, line 2, column 2: cannot parse \"3O\" as a number (invalid digit found in string)"
+        );
+    }
+
+    #[test]
+    fn rotation_reports() {
+        let mut dial = Dial::new(10, 3);
+        let report = |position, zeroes| RotationReport { position, zeroes };
+
+        assert_eq!(dial.rotate(Direction::Left, 3), report(0, 1));
+        assert_eq!(dial.rotate(Direction::Left, 10), report(0, 1));
+        assert_eq!(dial.rotate(Direction::Left, 1), report(9, 0));
+        assert_eq!(dial.rotate(Direction::Right, 1), report(0, 1));
+        assert_eq!(dial.rotate(Direction::Right, 25), report(5, 2));
+        assert_eq!(dial.rotate(Direction::Left, 26), report(9, 3));
+        assert_eq!(dial.rotate(Direction::Right, 0), report(9, 0));
+
+        dial.reset();
+        assert_eq!(dial.position(), 3);
+    }
+
+    #[test]
+    fn single_position_dial() {
+        let mut dial = Dial::new(1, 0);
+        assert_eq!(dial.rotate(Direction::Left, 7).zeroes, 7);
+        assert_eq!(dial.rotate(Direction::Right, 7).zeroes, 7);
+        assert_eq!(dial.position(), 0);
+    }
+
+    fn random_input(rng: &mut StdRng, size: u32) -> Vec<(Direction, u32)> {
+        (0..rng.random_range(0..30))
+            .map(|_| {
+                let dir = if rng.random_bool(0.5) {
+                    Direction::Left
+                } else {
+                    Direction::Right
+                };
+                (dir, rng.random_range(0..=3 * size))
+            })
+            .collect_vec()
+    }
+
+    #[test]
+    fn random_rotations() {
+        let mut rng = StdRng::seed_from_u64(2025);
+
+        for _ in 0..500 {
+            let size = rng.random_range(1..=120);
+            let start = rng.random_range(0..size);
+            let input = random_input(&mut rng, size);
+
+            let mut dial = Dial::new(size, start);
+            let reports = input
+                .iter()
+                .map(|&(dir, num)| dial.rotate(dir, num))
+                .collect_vec();
+            let landed = reports.iter().filter(|r| r.position == 0).count();
+            let clicked = reports.iter().map(|r| r.zeroes).sum();
+
+            assert_eq!(
+                (landed, clicked),
+                naive(size, start, &input),
+                "{:?} on a dial of size {} starting at {}",
+                input,
+                size,
+                start
+            );
+        }
+    }
+
+    #[test]
+    fn random_parts() {
+        let mut rng = StdRng::seed_from_u64(2025);
+
+        for _ in 0..500 {
+            let input = random_input(&mut rng, 100);
+            assert_eq!(
+                (part1(&input), part2(&input)),
+                naive(100, 50, &input),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn examples_match_naive() {
+        let input = parse(EXAMPLE).expect("Could not parse");
+        assert_eq!(naive(100, 50, &input), (part1(&input), part2(&input)));
+    }
+
+    #[test]
+    fn trace_example() {
+        let steps = trace(&parse(EXAMPLE).expect("Could not parse"));
+        let positions = steps.iter().map(|step| step.after).collect_vec();
+        assert_eq!(positions, [82, 52, 0, 95, 55, 0, 99, 0, 14, 32]);
+        assert!(
+            steps
+                .iter()
+                .tuple_windows()
+                .all(|(a, b)| a.after == b.before)
+        );
+
+        let csv = render_csv(&steps[..3]);
+        assert_eq!(
+            csv,
+            "instruction,before,after,zeroes\nL68,50,82,1\nL30,82,52,0\nR48,52,0,1\n"
+        );
+    }
+
+    #[test]
+    fn trace_table() {
+        let steps = trace(&[(Direction::Left, 68), (Direction::Right, 1050)]);
+        assert_eq!(
+            render_table(&steps),
+            "\
+| instruction | before | after | zeroes |
+| ----------- | ------ | ----- | ------ |
+| L68         |     50 |    82 |      1 |
+| R1050       |     82 |    32 |     11 |
+"
+        );
+    }
+
+    #[test]
+    fn dial_stack() {
+        let rotations =
+            parse_stack("0:R15\n1:L3\n0:L26\n2:R2\n0:R1000", 3).expect("Could not parse");
+        let mut stack = DialStack::new([Dial::new(10, 5), Dial::new(10, 0), Dial::new(4, 0)]);
+        let positions = |stack: &DialStack| stack.dials().iter().map(Dial::position).collect_vec();
+
+        // 5 + 15 wraps twice
+        stack.rotate(rotations[0]);
+        assert_eq!(positions(&stack), [0, 2, 0]);
+        assert_eq!(stack.zeroes(), [2, 0, 0]);
+
+        // 2 - 3 borrows once from the last dial, which wraps around too
+        stack.rotate(rotations[1]);
+        assert_eq!(positions(&stack), [0, 9, 3]);
+        assert_eq!(stack.zeroes(), [2, 1, 0]);
+
+        // 0 - 26 lands on 0 twice but borrows 3 times (0 -> 9 is the first), 9 - 3 does not
+        stack.rotate(rotations[2]);
+        assert_eq!(positions(&stack), [4, 6, 3]);
+        assert_eq!(stack.zeroes(), [4, 1, 0]);
+
+        stack.rotate(rotations[3]);
+        assert_eq!(positions(&stack), [4, 6, 1]);
+        assert_eq!((stack.zeroes()[2], stack.overflows()), (1, 2));
+
+        // 4 + 1000 wraps 100 times, 6 + 100 wraps 10 times, 1 + 10 wraps twice
+        stack.rotate(rotations[4]);
+        assert_eq!(positions(&stack), [4, 6, 3]);
+        assert_eq!(stack.zeroes(), [104, 11, 3]);
+        assert_eq!(stack.overflows(), 4);
+    }
+
+    #[test]
+    fn single_dial_stack() {
+        let input = parse(EXAMPLE).expect("Could not parse");
+        let mut stack = DialStack::new([Dial::default()]);
+        for &(direction, amount) in &input {
+            stack.rotate(StackRotation {
+                dial: 0,
+                direction,
+                amount,
+            });
+        }
+
+        assert_eq!(stack.zeroes(), [part2(&input)]);
+    }
+
+    #[test]
+    fn stack_parse_errors() {
+        let err = parse_stack("0:R1\n3:L2", 3).expect_err("Parsed a missing dial");
+        assert_eq!(
+            err.to_string(),
+            "day 1, line 2, column 1: invalid \"3\", the stack has 3 dials"
+        );
+
+        let err = parse_stack("0:R1\nL2", 3).expect_err("Parsed a missing index");
+        assert_eq!(
+            err.to_string(),
+            "day 1, line 2, column 1: unexpected \"L2\", expected '<dial>:<rotation>'"
+        );
     }
 }
@@ -1,36 +1,277 @@
+use std::{fmt, num::ParseIntError, ops::RangeInclusive};
+
 use itertools::Itertools;
+use num_bigint::BigUint;
+#[cfg(test)]
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use crate::{
+    input::{self, ParseError},
+    ranges::{Bound, merge_ranges},
+};
+
+/// Integers ids can be stored in, everything is computed on `u128` (and sums on `BigUint`)
+pub trait IdNumber: Bound + Into<u128> + fmt::Debug {
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError>;
+}
+
+impl IdNumber for u64 {
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError> {
+        u64::from_str_radix(src, radix)
+    }
+}
+
+impl IdNumber for u128 {
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError> {
+        u128::from_str_radix(src, radix)
+    }
+}
+
+/// Parses ranges of ids written in `radix` (from 2 to 36), to give to `sum_invalid` or
+/// `invalid_ids`
+pub fn parse_ranges<T: IdNumber>(input: &str, radix: u32) -> Result<Vec<(T, T)>, ParseError> {
+    assert!(
+        (2..=36).contains(&radix),
+        "Radix {} is not in 2..=36",
+        radix
+    );
+
+    input::lines(2, input)
+        .flat_map(|line| {
+            line.text.split(',').map(move |range| {
+                let range = range.trim();
+                let Some((l, r)) = range.split_once('-') else {
+                    return Err(line.unexpected(range, "a range like '11-22'"));
+                };
+
+                let parse = |num: &str| line.parse_with(num, |n| T::from_str_radix(n, radix));
+                Ok((parse(l.trim())?, parse(r.trim())?))
+            })
+        })
+        .try_collect()
+}
+
 #[aoc_generator(day02)]
-fn parse(input: &str) -> Vec<(u64, u64)> {
-    input
-        .split(',')
-        .map(str::trim)
-        .map(|range| {
-            let (l, r) = range.split_once('-').expect("Invalid range");
-            (
-                l.trim().parse().expect("Cannot parse u64"),
-                r.trim().parse().expect("Cannot parse u64"),
-            )
+fn parse(input: &str) -> Result<Vec<(u128, u128)>, ParseError> {
+    Ok(merge_ranges(&parse_ranges(input, 10)?))
+}
+
+fn digit_count(num: u128, radix: u32) -> u32 {
+    num.checked_ilog(radix as u128).unwrap_or_default() + 1
+}
+
+/// `1 0..01 0..01 ...` in `radix`, multiplying a `block` digits long number by it repeats the
+/// number until it is `digits` long
+fn repunit(digits: u32, block: u32, radix: u32) -> u128 {
+    let shift = (radix as u128).pow(block);
+    (0..(digits / block)).fold(0, |acc, _| acc * shift + 1)
+}
+
+/// The `block` digits long numbers that, repeated until `digits` long, land in `[lower, upper]`
+fn block_range(
+    (lower, upper): (u128, u128),
+    digits: u32,
+    block: u32,
+    radix: u32,
+) -> RangeInclusive<u128> {
+    let repunit = repunit(digits, block, radix);
+    let first = lower.div_ceil(repunit).max((radix as u128).pow(block - 1));
+    let last = (upper / repunit).min((radix as u128).pow(block) - 1);
+
+    first..=last
+}
+
+/// Sum of the `digits` long numbers in `[lower, upper]` made of a `block` digits long block
+/// repeated `digits / block` times
+///
+/// These are the multiples of the repunit by a `block` digits long number, so their sum is an
+/// arithmetic series
+fn repeated_block_sum(range: (u128, u128), digits: u32, block: u32, radix: u32) -> BigUint {
+    let blocks = block_range(range, digits, block, radix);
+    let (first, last) = (*blocks.start(), *blocks.end());
+    if first > last {
+        return BigUint::ZERO;
+    }
+
+    (BigUint::from(first) + last) * (last - first + 1) / 2u32 * repunit(digits, block, radix)
+}
+
+/// Length of the smallest block that `num` (which is `digits` long) is a repetition of
+fn smallest_block(num: u128, digits: u32, radix: u32) -> u32 {
+    (1..digits)
+        .filter(|block| digits.is_multiple_of(*block))
+        .find(|&block| {
+            let head = num / (radix as u128).pow(digits - block);
+            head * repunit(digits, block, radix) == num
+        })
+        .unwrap_or(digits)
+}
+
+/// Which repetitions make an id invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// A block repeated exactly twice (part 1)
+    Twice,
+    /// A block repeated at least twice (part 2)
+    AtLeastTwice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InvalidId {
+    pub number: u128,
+    /// With `Mode::AtLeastTwice` this is the smallest block, `1111` is `1` repeated 4 times
+    pub block: u128,
+    pub repetitions: u32,
+}
+
+/// Every invalid id in `range` (with the digits of `radix`), in ascending order
+///
+/// Numbers with a different amount of digits never interleave, and for a given amount of digits
+/// each block length yields an ascending sequence, so these only need to be merged. With
+/// `Mode::AtLeastTwice` blocks that are themselves repetitions are skipped, they are generated
+/// by a smaller block length already.
+pub fn invalid_ids<T: IdNumber>(
+    range: (T, T),
+    mode: Mode,
+    radix: u32,
+) -> impl Iterator<Item = InvalidId> {
+    assert!(
+        (2..=36).contains(&radix),
+        "Radix {} is not in 2..=36",
+        radix
+    );
+    let range: (u128, u128) = (range.0.into(), range.1.into());
+
+    digit_counts(range, radix).flat_map(move |digits| {
+        let blocks = match mode {
+            Mode::Twice if digits.is_multiple_of(2) => vec![digits / 2],
+            Mode::Twice => vec![],
+            Mode::AtLeastTwice => (1..digits)
+                .filter(|block| digits.is_multiple_of(*block))
+                .collect(),
+        };
+
+        blocks
+            .into_iter()
+            .map(move |block| {
+                let repunit = repunit(digits, block, radix);
+                block_range(range, digits, block, radix)
+                    .filter(move |&b| {
+                        mode == Mode::Twice || smallest_block(b, block, radix) == block
+                    })
+                    .map(move |b| InvalidId {
+                        number: b * repunit,
+                        block: b,
+                        repetitions: digits / block,
+                    })
+            })
+            .kmerge_by(|a, b| a.number < b.number)
+    })
+}
+
+fn mobius(mut n: u32) -> i32 {
+    let mut res = 1;
+    let mut p = 2;
+
+    while p * p <= n {
+        if n.is_multiple_of(p) {
+            n /= p;
+            if n.is_multiple_of(p) {
+                return 0;
+            }
+
+            res = -res;
+        }
+
+        p += 1;
+    }
+
+    if n > 1 { -res } else { res }
+}
+
+fn digit_counts<T: IdNumber>((lower, upper): (T, T), radix: u32) -> RangeInclusive<u32> {
+    digit_count(lower.into(), radix)..=digit_count(upper.into(), radix)
+}
+
+fn part1_sum_range<T: IdNumber>(range: (T, T), radix: u32) -> BigUint {
+    let wide = (range.0.into(), range.1.into());
+
+    digit_counts(range, radix)
+        .filter(|digits| digits.is_multiple_of(2))
+        .map(|digits| repeated_block_sum(wide, digits, digits / 2, radix))
+        .sum()
+}
+
+/// A number with a block repeated with `block` dividing both `a` and `b` is counted for both, so
+/// the sums for every block length are combined with inclusion-exclusion (Möbius inversion)
+fn part2_sum_range<T: IdNumber>(range: (T, T), radix: u32) -> BigUint {
+    let wide = (range.0.into(), range.1.into());
+    let mut added = BigUint::ZERO;
+    let mut removed = BigUint::ZERO;
+
+    for digits in digit_counts(range, radix) {
+        for block in (1..digits).filter(|block| digits.is_multiple_of(*block)) {
+            let sum = repeated_block_sum(wide, digits, block, radix);
+            match mobius(digits / block) {
+                -1 => added += sum,
+                1 => removed += sum,
+                _ => (),
+            }
+        }
+    }
+
+    added - removed
+}
+
+/// How ids that are in several of the listed ranges are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overlaps {
+    /// Every listed range is summed on its own, so these ids are counted once per range
+    PerRange,
+    /// The ranges are merged before summing, so these ids are counted once
+    Union,
+}
+
+pub fn sum_invalid<T: IdNumber>(
+    ranges: &[(T, T)],
+    mode: Mode,
+    radix: u32,
+    overlaps: Overlaps,
+) -> BigUint {
+    let merged;
+    let ranges = match overlaps {
+        Overlaps::PerRange => ranges,
+        Overlaps::Union => {
+            merged = merge_ranges(ranges);
+            &merged
+        }
+    };
+
+    ranges
+        .iter()
+        .map(|&range| match mode {
+            Mode::Twice => part1_sum_range(range, radix),
+            Mode::AtLeastTwice => part2_sum_range(range, radix),
         })
-        .collect_vec()
+        .sum()
 }
 
 #[aoc(day02, part1)]
-fn part1(input: &[(u64, u64)]) -> u64 {
-    input.iter().copied().map(part1_check_range).sum()
+fn part1(input: &[(u128, u128)]) -> BigUint {
+    sum_invalid(input, Mode::Twice, 10, Overlaps::Union)
 }
 
-fn part1_check_range(range: (u64, u64)) -> u64 {
-    (range.0..=range.1)
+#[cfg(test)]
+fn part1_check_range((lower, upper): (u128, u128), radix: u32) -> u128 {
+    (lower..=upper)
         .into_par_iter()
-        .filter(|&n| !part1_is_valid(n))
+        .filter(|&n| !part1_is_valid(n, radix))
         .sum()
 }
 
-fn part1_is_valid(num: u64) -> bool {
-    let v = num as f64;
-    let size = v.log10().floor() as u32 + 1;
+#[cfg(test)]
+fn part1_is_valid(num: u128, radix: u32) -> bool {
+    let size = digit_count(num, radix);
 
     if !size.is_multiple_of(2) {
         return true;
@@ -38,7 +279,7 @@ fn part1_is_valid(num: u64) -> bool {
 
     let size = size / 2;
 
-    let middle = u64::pow(10, size);
+    let middle = u128::pow(radix as u128, size);
     let lower = num % middle;
     let upper = num / middle;
 
@@ -46,20 +287,21 @@ fn part1_is_valid(num: u64) -> bool {
 }
 
 #[aoc(day02, part2)]
-fn part2(input: &[(u64, u64)]) -> u64 {
-    input.iter().copied().map(part2_check_range).sum()
+fn part2(input: &[(u128, u128)]) -> BigUint {
+    sum_invalid(input, Mode::AtLeastTwice, 10, Overlaps::Union)
 }
 
-fn part2_check_range(range: (u64, u64)) -> u64 {
-    (range.0..=range.1)
+#[cfg(test)]
+fn part2_check_range((lower, upper): (u128, u128), radix: u32) -> u128 {
+    (lower..=upper)
         .into_par_iter()
-        .filter(|&n| !part2_is_valid(n))
+        .filter(|&n| !part2_is_valid(n, radix))
         .sum()
 }
 
-fn part2_is_valid(num: u64) -> bool {
-    let v = num as f64;
-    let size = v.log10().floor() as u32 + 1;
+#[cfg(test)]
+fn part2_is_valid(num: u128, radix: u32) -> bool {
+    let size = digit_count(num, radix);
 
     'outer: for i in 2..=size {
         if !size.is_multiple_of(i) {
@@ -67,7 +309,7 @@ fn part2_is_valid(num: u64) -> bool {
         }
 
         let fraction = size / i;
-        let parts_divider = u64::pow(10, fraction);
+        let parts_divider = u128::pow(radix as u128, fraction);
 
         let expected = num % parts_divider;
         let mut n = num;
@@ -90,106 +332,401 @@ mod tests {
 
     #[test]
     fn part1_example1() {
-        assert_eq!(part1(&[(11, 22)]), 33);
+        assert_eq!(part1(&[(11, 22)]), 33u32.into());
     }
 
     #[test]
     fn part1_example2() {
-        assert_eq!(part1(&[(95, 115)]), 99);
+        assert_eq!(part1(&[(95, 115)]), 99u32.into());
     }
 
     #[test]
     fn part1_example3() {
-        assert_eq!(part1(&[(998, 1012)]), 1010);
+        assert_eq!(part1(&[(998, 1012)]), 1010u32.into());
     }
 
     #[test]
     fn part1_example4() {
-        assert_eq!(part1(&[(1188511880, 1188511890)]), 1188511885);
+        assert_eq!(part1(&[(1188511880, 1188511890)]), 1188511885u32.into());
     }
 
     #[test]
     fn part1_example5() {
-        assert_eq!(part1(&[(222220, 222224)]), 222222);
+        assert_eq!(part1(&[(222220, 222224)]), 222222u32.into());
     }
 
     #[test]
     fn part1_example6() {
-        assert_eq!(part1(&[(1698522, 1698528)]), 0);
+        assert_eq!(part1(&[(1698522, 1698528)]), 0u32.into());
     }
 
     #[test]
     fn part1_example7() {
-        assert_eq!(part1(&[(446443, 446449)]), 446446);
+        assert_eq!(part1(&[(446443, 446449)]), 446446u32.into());
     }
 
     #[test]
     fn part1_example8() {
-        assert_eq!(part1(&[(38593856, 38593862)]), 38593859);
+        assert_eq!(part1(&[(38593856, 38593862)]), 38593859u32.into());
     }
 
     #[test]
     fn part1_examples_rest() {
         assert_eq!(
-            part1(&parse(
-                "565653-565659,824824821-824824827,2121212118-2121212124"
-            )),
-            0
+            part1(
+                &parse("565653-565659,824824821-824824827,2121212118-2121212124")
+                    .expect("Could not parse")
+            ),
+            BigUint::ZERO
         );
     }
 
     #[test]
     fn part2_example1() {
-        assert_eq!(part2_check_range((11, 22)), 11 + 22);
+        assert_eq!(part2_check_range((11, 22), 10), 11 + 22);
     }
 
     #[test]
     fn part2_example2() {
-        assert_eq!(part2_check_range((95, 115)), 99 + 111);
+        assert_eq!(part2_check_range((95, 115), 10), 99 + 111);
     }
 
     #[test]
     fn part2_example3() {
-        assert_eq!(part2_check_range((998, 1012)), 999 + 1010);
+        assert_eq!(part2_check_range((998, 1012), 10), 999 + 1010);
     }
 
     #[test]
     fn part2_example4() {
-        assert_eq!(part2_check_range((1188511880, 1188511890)), 1188511885);
+        assert_eq!(part2_check_range((1188511880, 1188511890), 10), 1188511885);
     }
 
     #[test]
     fn part2_example5() {
-        assert_eq!(part2_check_range((222220, 222224)), 222222);
+        assert_eq!(part2_check_range((222220, 222224), 10), 222222);
     }
 
     #[test]
     fn part2_example6() {
-        assert_eq!(part2_check_range((1698522, 1698528)), 0);
+        assert_eq!(part2_check_range((1698522, 1698528), 10), 0);
     }
 
     #[test]
     fn part2_example7() {
-        assert_eq!(part2_check_range((446443, 446449)), 446446);
+        assert_eq!(part2_check_range((446443, 446449), 10), 446446);
     }
 
     #[test]
     fn part2_example8() {
-        assert_eq!(part2_check_range((38593856, 38593862)), 38593859);
+        assert_eq!(part2_check_range((38593856, 38593862), 10), 38593859);
     }
 
     #[test]
     fn part2_example9() {
-        assert_eq!(part2_check_range((565653, 565659)), 565656);
+        assert_eq!(part2_check_range((565653, 565659), 10), 565656);
     }
 
     #[test]
     fn part2_example10() {
-        assert_eq!(part2_check_range((824824821, 824824827)), 824824824);
+        assert_eq!(part2_check_range((824824821, 824824827), 10), 824824824);
     }
 
     #[test]
     fn part2_example11() {
-        assert_eq!(part2_check_range((2121212118, 2121212124)), 2121212121);
+        assert_eq!(part2_check_range((2121212118, 2121212124), 10), 2121212121);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse("11-22,95115").expect_err("Parsed a range without '-'");
+        assert_eq!(
+            err.to_string(),
+            "day 2, line 1, column 7: unexpected \"95115\", expected a range like '11-22'"
+        );
+    }
+
+    #[test]
+    fn mobius_values() {
+        let values = (1..=12).map(mobius).collect_vec();
+        assert_eq!(values, [1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0]);
+    }
+
+    #[test]
+    fn closed_form_examples() {
+        let input = parse(
+            "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,\
+            1698522-1698528,446443-446449,38593856-38593862,565653-565659,\
+            824824821-824824827,2121212118-2121212124",
+        )
+        .expect("Could not parse");
+
+        assert_eq!(part1(&input), 1227775554u64.into());
+        assert_eq!(part2(&input), 4174379265u64.into());
+    }
+
+    #[test]
+    fn closed_form_matches_brute_force() {
+        let ranges = [
+            (1, 1_000),
+            (1, 120_000),
+            (9_999, 10_001),
+            (99_000, 1_001_000),
+            (123_123, 123_123),
+            (121_000, 1_212_200),
+            (5_000_000, 5_300_000),
+        ];
+
+        for range in ranges {
+            assert_eq!(
+                part1_sum_range(range, 10),
+                part1_check_range(range, 10).into(),
+                "{:?}",
+                range
+            );
+            assert_eq!(
+                part2_sum_range(range, 10),
+                part2_check_range(range, 10).into(),
+                "{:?}",
+                range
+            );
+        }
+    }
+
+    #[test]
+    fn closed_form_huge_range() {
+        // 11 is prime so the only 11 digits invalid ids are repeated single digits
+        assert_eq!(
+            part2_sum_range((10_000_000_000u64, 99_999_999_999), 10),
+            (1..=9u64).map(|d| d * 11_111_111_111).sum::<u64>().into()
+        );
+
+        // Blocks of 10 digits repeated twice: 9 * 10^9 ids, far too many to iterate
+        let first = 1_000_000_000 * 10_000_000_001u128;
+        let last = 1_844_674_407 * 10_000_000_001u128;
+        assert_eq!(
+            part1_sum_range((10_000_000_000_000_000_000, u64::MAX), 10),
+            ((first + last) * (1_844_674_407 - 1_000_000_000 + 1) / 2).into()
+        );
+    }
+
+    #[test]
+    fn power_of_ten_boundaries() {
+        for exponent in 1..=38 {
+            let power = 10u128.pow(exponent);
+            let range = (power - 2, power + 2);
+
+            assert_eq!(digit_count(power - 1, 10), exponent);
+            assert_eq!(digit_count(power, 10), exponent + 1);
+            assert_eq!(
+                part1_sum_range(range, 10),
+                part1_check_range(range, 10).into(),
+                "{:?}",
+                range
+            );
+            assert_eq!(
+                part2_sum_range(range, 10),
+                part2_check_range(range, 10).into(),
+                "{:?}",
+                range
+            );
+        }
+    }
+
+    #[test]
+    fn u64_and_u128_agree() {
+        let input = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224";
+        let narrow = parse_ranges::<u64>(input, 10).expect("Could not parse");
+        let wide = parse_ranges::<u128>(input, 10).expect("Could not parse");
+
+        for (&n, &w) in narrow.iter().zip(&wide) {
+            assert_eq!(part1_sum_range(n, 10), part1_sum_range(w, 10));
+            assert_eq!(part2_sum_range(n, 10), part2_sum_range(w, 10));
+        }
+
+        assert!(parse_ranges::<u64>("1-100000000000000000000", 10).is_err());
+    }
+
+    #[test]
+    fn wide_ranges() {
+        let ranges =
+            parse("12345678901234567890123456789012345678-99999999999999999999999999999999999999")
+                .expect("Could not parse");
+
+        // Blocks of 19 digits repeated twice, every 19 digits block from the first one to 10^19 - 1
+        let first = 1234567890123456789u128;
+        let last = 9999999999999999999u128;
+        let repunit = 10u128.pow(19) + 1;
+        let expected = (BigUint::from(first) + last) * (last - first + 1) / 2u32 * repunit;
+
+        assert_eq!(part1(&ranges), expected);
+        assert!(part2(&ranges) > expected);
+    }
+
+    #[test]
+    fn invalid_ids_structure() {
+        let ids = invalid_ids((95u64, 1012), Mode::AtLeastTwice, 10).collect_vec();
+        assert_eq!(
+            ids,
+            [
+                InvalidId {
+                    number: 99,
+                    block: 9,
+                    repetitions: 2
+                },
+                InvalidId {
+                    number: 111,
+                    block: 1,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 222,
+                    block: 2,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 333,
+                    block: 3,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 444,
+                    block: 4,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 555,
+                    block: 5,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 666,
+                    block: 6,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 777,
+                    block: 7,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 888,
+                    block: 8,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 999,
+                    block: 9,
+                    repetitions: 3
+                },
+                InvalidId {
+                    number: 1010,
+                    block: 10,
+                    repetitions: 2
+                },
+            ]
+        );
+
+        let twice = invalid_ids((1100u64, 1111), Mode::Twice, 10).collect_vec();
+        assert_eq!(
+            twice,
+            [InvalidId {
+                number: 1111,
+                block: 11,
+                repetitions: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_ids_match_sums() {
+        let ranges = [(1u128, 1_000), (99_000, 1_001_000), (121_000, 1_212_200)];
+
+        for range in ranges {
+            for (mode, sum) in [
+                (Mode::Twice, part1_sum_range(range, 10)),
+                (Mode::AtLeastTwice, part2_sum_range(range, 10)),
+            ] {
+                let ids = invalid_ids(range, mode, 10).collect_vec();
+                assert!(ids.iter().tuple_windows().all(|(a, b)| a.number < b.number));
+
+                for id in &ids {
+                    let block = digit_count(id.block, 10);
+                    let digits = block * id.repetitions;
+                    assert_eq!(id.block * repunit(digits, block, 10), id.number, "{:?}", id);
+                }
+
+                let total: u128 = ids.iter().map(|id| id.number).sum();
+                assert_eq!(BigUint::from(total), sum, "{:?}", range);
+            }
+        }
+    }
+
+    #[test]
+    fn other_radixes() {
+        // 0xabab and 0x77 are the only ones repeated twice, 0x777 is repeated 3 times
+        let ranges = parse_ranges::<u64>("70-80,700-7ff,ab00-abff", 16).expect("Could not parse");
+        assert_eq!(ranges, [(0x70, 0x80), (0x700, 0x7ff), (0xab00, 0xabff)]);
+
+        let part1: BigUint = ranges.iter().map(|&r| part1_sum_range(r, 16)).sum();
+        let part2: BigUint = ranges.iter().map(|&r| part2_sum_range(r, 16)).sum();
+        assert_eq!(part1, BigUint::from(0x77u32 + 0xabab));
+        assert_eq!(part2, BigUint::from(0x77u32 + 0x777 + 0xabab));
+
+        let binary = invalid_ids((0b1000u64, 0b1111), Mode::AtLeastTwice, 2).collect_vec();
+        assert_eq!(
+            binary,
+            [
+                InvalidId {
+                    number: 0b1010,
+                    block: 0b10,
+                    repetitions: 2
+                },
+                InvalidId {
+                    number: 0b1111,
+                    block: 0b1,
+                    repetitions: 4
+                },
+            ]
+        );
+
+        assert!(parse_ranges::<u64>("12-1g", 16).is_err());
+    }
+
+    #[test]
+    fn other_radixes_match_brute_force() {
+        for radix in [2, 3, 7, 16, 36] {
+            let range = (1, 50_000);
+            assert_eq!(
+                part1_sum_range(range, radix),
+                part1_check_range(range, radix).into(),
+                "radix {}",
+                radix
+            );
+            assert_eq!(
+                part2_sum_range(range, radix),
+                part2_check_range(range, radix).into(),
+                "radix {}",
+                radix
+            );
+        }
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        let listed = parse_ranges::<u64>("11-22,15-99,11-22", 10).expect("Could not parse");
+        let sum = |mode, overlaps| sum_invalid(&listed, mode, 10, overlaps);
+
+        // 11 is in 2 of the listed ranges and 22 in all 3
+        let union: u32 = (1..=9).map(|d| d * 11).sum();
+        assert_eq!(sum(Mode::Twice, Overlaps::Union), union.into());
+        assert_eq!(
+            sum(Mode::Twice, Overlaps::PerRange),
+            (union + 11 + 2 * 22).into()
+        );
+        assert_eq!(sum(Mode::AtLeastTwice, Overlaps::Union), union.into());
+
+        let merged = parse("11-22,15-99,11-22").expect("Could not parse");
+        assert_eq!(merged, [(11, 99)]);
+        assert_eq!(part1(&merged), union.into());
     }
 }
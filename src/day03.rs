@@ -1,116 +1,489 @@
+use std::{error::Error, fmt};
+
 use itertools::Itertools;
+use num_bigint::BigUint;
+
+use crate::input::{self, InputLine, ParseError};
 
-fn parse_line(input_line: &[u8]) -> Vec<u8> {
-    input_line
-        .iter()
-        .filter_map(|&b| match b {
-            b'0'..=b'9' => Some(b - b'0'),
-            _ => None,
+fn parse_line(line: InputLine) -> Result<Vec<u8>, ParseError> {
+    line.text
+        .char_indices()
+        .map(|(i, c)| match c {
+            '0'..='9' => Ok(c as u8 - b'0'),
+            _ => Err(line.unexpected(&line.text[i..(i + c.len_utf8())], "a digit")),
         })
-        .collect_vec()
+        .try_collect()
 }
 
-fn first_position_max(line: &[u8]) -> usize {
-    let mut idx = 0;
+/// Why a selection cannot be made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelectionError {
+    /// The bank has fewer than `needed` batteries once the forbidden ones are left out
+    TooShort { available: usize, needed: usize },
+    /// The mandatory battery is outside the bank, forbidden, or no battery is selected at all
+    Unavailable { index: usize },
+}
 
-    for (i, v) in line.iter().enumerate().skip(1) {
-        if line[idx] < *v {
-            idx = i;
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionError::TooShort { available, needed } => {
+                write!(f, "cannot select {} batteries out of {}", needed, available)
+            }
+            SelectionError::Unavailable { index } => {
+                write!(f, "battery {} cannot be selected", index)
+            }
         }
     }
+}
+
+impl Error for SelectionError {}
 
-    idx
+/// Joltage of a selection, it only needs a big number past 38 digits
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Joltage {
+    Small(u128),
+    Big(BigUint),
 }
 
-fn part1_best_pair_in_line(line: &[u8]) -> u32 {
-    let first_idx = first_position_max(&line[..(line.len() - 1)]);
-    let second_num = *line[(first_idx + 1)..].iter().max().expect("Empty line???");
+impl Joltage {
+    const MAX_SMALL_DIGITS: usize = 38;
+
+    fn from_digits(digits: impl ExactSizeIterator<Item = u8>) -> Self {
+        if digits.len() <= Self::MAX_SMALL_DIGITS {
+            Self::Small(digits.fold(0, |acc, d| acc * 10 + u128::from(d)))
+        } else {
+            Self::Big(digits.fold(BigUint::ZERO, |acc, d| acc * 10u8 + d))
+        }
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        match self {
+            Self::Small(value) => Some(*value),
+            Self::Big(value) => value.try_into().ok(),
+        }
+    }
+
+    pub fn to_biguint(&self) -> BigUint {
+        match self {
+            Self::Small(value) => BigUint::from(*value),
+            Self::Big(value) => value.clone(),
+        }
+    }
+}
 
-    (line[first_idx] * 10 + second_num) as u32
+impl fmt::Display for Joltage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Small(value) => value.fmt(f),
+            Self::Big(value) => value.fmt(f),
+        }
+    }
 }
 
-fn part2_best_joltage(line: &[u8]) -> u64 {
-    let mut sum = 0;
-    let mut current_idx = 0;
-    for i in (0..12).rev() {
-        let line_segment = &line[current_idx..(line.len() - i)];
-        let new_max = first_position_max(line_segment);
+/// Batteries turned on in a bank, `indices` are increasing
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Selection {
+    pub value: Joltage,
+    pub indices: Vec<usize>,
+}
 
-        sum *= 10;
-        sum += line_segment[new_max] as u64;
-        current_idx += new_max + 1;
+impl Selection {
+    fn new(line: &[u8], indices: Vec<usize>) -> Self {
+        Self {
+            value: Joltage::from_digits(indices.iter().map(|&i| line[i])),
+            indices,
+        }
     }
+}
+
+/// Whether a selection should make the largest or the smallest number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Goal {
+    #[default]
+    Max,
+    Min,
+}
+
+impl Goal {
+    /// Whether `digit` should replace `kept` earlier in the number
+    fn prefers(self, digit: u8, kept: u8) -> bool {
+        match self {
+            Goal::Max => digit > kept,
+            Goal::Min => digit < kept,
+        }
+    }
+
+    /// Whether the digits of `kept` make a number at least as good as the ones of `other`
+    fn prefers_digits(self, kept: &[u8], other: &[u8]) -> bool {
+        match self {
+            Goal::Max => kept >= other,
+            Goal::Min => kept <= other,
+        }
+    }
+}
+
+/// Positions a selection has to avoid or to use
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Constraints {
+    pub forbidden: Vec<usize>,
+    pub mandatory: Option<usize>,
+}
+
+/// Keeps `k` of the `candidates` (increasing indices of `line`) in order
+///
+/// Keeps a stack of the digits picked so far: a digit pops every kept one it is preferred to as
+/// long as enough digits remain to fill the `k` places, so each digit is pushed and popped at
+/// most once
+fn stack_select(
+    line: &[u8],
+    candidates: &[usize],
+    k: usize,
+    goal: Goal,
+) -> Result<Vec<usize>, SelectionError> {
+    let mut droppable = candidates
+        .len()
+        .checked_sub(k)
+        .ok_or(SelectionError::TooShort {
+            available: candidates.len(),
+            needed: k,
+        })?;
+    let mut stack: Vec<usize> = Vec::with_capacity(candidates.len());
+
+    for &i in candidates {
+        while droppable > 0
+            && stack
+                .last()
+                .is_some_and(|&top| goal.prefers(line[i], line[top]))
+        {
+            stack.pop();
+            droppable -= 1;
+        }
+
+        stack.push(i);
+    }
+
+    stack.truncate(k);
+    Ok(stack)
+}
+
+/// Best number made of `k` digits of `line` kept in order
+///
+/// A mandatory battery splits the line in two: every split of the other `k - 1` digits between
+/// both sides is tried, which makes it O(n·k) instead of O(n)
+pub fn select_k_digits(
+    line: &[u8],
+    k: usize,
+    goal: Goal,
+    constraints: &Constraints,
+) -> Result<Selection, SelectionError> {
+    let allowed = (0..line.len())
+        .filter(|i| !constraints.forbidden.contains(i))
+        .collect_vec();
+
+    let Some(mandatory) = constraints.mandatory else {
+        return Ok(Selection::new(line, stack_select(line, &allowed, k, goal)?));
+    };
+
+    let unavailable = SelectionError::Unavailable { index: mandatory };
+    let split = allowed.binary_search(&mandatory).map_err(|_| unavailable)?;
+    let others = k.checked_sub(1).ok_or(unavailable)?;
+    if allowed.len() < k {
+        return Err(SelectionError::TooShort {
+            available: allowed.len(),
+            needed: k,
+        });
+    }
+
+    let (before, after) = (&allowed[..split], &allowed[(split + 1)..]);
+    let splits: Vec<(Vec<u8>, Vec<usize>)> = (others.saturating_sub(after.len())
+        ..=others.min(before.len()))
+        .map(|count| {
+            let mut indices = stack_select(line, before, count, goal)?;
+            indices.push(mandatory);
+            indices.extend(stack_select(line, after, others - count, goal)?);
+            Ok::<_, SelectionError>((indices.iter().map(|&i| line[i]).collect(), indices))
+        })
+        .try_collect()?;
+
+    let (_, indices) = splits
+        .into_iter()
+        .reduce(|best, other| {
+            if goal.prefers_digits(&best.0, &other.0) {
+                best
+            } else {
+                other
+            }
+        })
+        .expect("Both sides together have enough batteries");
+
+    Ok(Selection::new(line, indices))
+}
+
+/// Largest number made of `k` digits of `line` kept in order
+pub fn best_k_digits(line: &[u8], k: usize) -> Result<Selection, SelectionError> {
+    select_k_digits(line, k, Goal::Max, &Constraints::default())
+}
+
+/// Smallest number made of `k` digits of `line` kept in order
+pub fn worst_k_digits(line: &[u8], k: usize) -> Result<Selection, SelectionError> {
+    select_k_digits(line, k, Goal::Min, &Constraints::default())
+}
+
+fn part1_best_pair_in_line(line: &[u8]) -> Result<u32, SelectionError> {
+    let best = best_k_digits(line, 2)?;
+    Ok(best.value.to_u128().expect("2 digits fit a u128") as u32)
+}
 
-    sum
+fn part2_best_joltage(line: &[u8]) -> Result<u64, SelectionError> {
+    let best = best_k_digits(line, 12)?;
+    Ok(best.value.to_u128().expect("12 digits fit a u128") as u64)
 }
 
 #[aoc_generator(day03)]
-fn parse(input: &str) -> Vec<Vec<u8>> {
-    input
-        .lines()
-        .map(str::trim)
-        .filter(|line| !line.is_empty())
-        .map(|line| parse_line(line.as_bytes()))
-        .filter(|line| !line.is_empty())
-        .collect_vec()
+fn parse(input: &str) -> Result<Vec<Vec<u8>>, ParseError> {
+    input::lines(3, input).map(parse_line).try_collect()
 }
 
 #[aoc(day03, part1)]
-fn part1(input: &[Vec<u8>]) -> u32 {
+fn part1(input: &[Vec<u8>]) -> Result<u32, SelectionError> {
     input.iter().map(|line| part1_best_pair_in_line(line)).sum()
 }
 
 #[aoc(day03, part2)]
-fn part2(input: &[Vec<u8>]) -> u64 {
+fn part2(input: &[Vec<u8>]) -> Result<u64, SelectionError> {
     input.iter().map(|line| part2_best_joltage(line)).sum()
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
     use super::*;
 
-    const EXAMPLE1: &[u8] = b"987654321111111";
-    const EXAMPLE2: &[u8] = b"811111111111119";
-    const EXAMPLE3: &[u8] = b"234234234234278";
-    const EXAMPLE4: &[u8] = b"818181911112111";
+    fn bank(text: &str) -> Vec<u8> {
+        parse_line(InputLine::new(3, 1, text)).expect("Could not parse")
+    }
+
+    /// Digits of the best selection, found by trying every combination
+    fn brute_force(
+        line: &[u8],
+        k: usize,
+        goal: Goal,
+        constraints: &Constraints,
+    ) -> Option<Vec<u8>> {
+        let digits = (0..line.len())
+            .combinations(k)
+            .filter(|indices| {
+                indices.iter().all(|i| !constraints.forbidden.contains(i))
+                    && constraints.mandatory.is_none_or(|m| indices.contains(&m))
+            })
+            .map(|indices| indices.iter().map(|&i| line[i]).collect_vec());
+
+        match goal {
+            Goal::Max => digits.max(),
+            Goal::Min => digits.min(),
+        }
+    }
+
+    const EXAMPLE1: &str = "987654321111111";
+    const EXAMPLE2: &str = "811111111111119";
+    const EXAMPLE3: &str = "234234234234278";
+    const EXAMPLE4: &str = "818181911112111";
 
     #[test]
     fn part1_example1() {
-        assert_eq!(part1_best_pair_in_line(&parse_line(EXAMPLE1)), 98);
+        assert_eq!(part1_best_pair_in_line(&bank(EXAMPLE1)), Ok(98));
     }
 
     #[test]
     fn part1_example2() {
-        assert_eq!(part1_best_pair_in_line(&parse_line(EXAMPLE2)), 89);
+        assert_eq!(part1_best_pair_in_line(&bank(EXAMPLE2)), Ok(89));
     }
 
     #[test]
     fn part1_example3() {
-        assert_eq!(part1_best_pair_in_line(&parse_line(EXAMPLE3)), 78);
+        assert_eq!(part1_best_pair_in_line(&bank(EXAMPLE3)), Ok(78));
     }
 
     #[test]
     fn part1_example4() {
-        assert_eq!(part1_best_pair_in_line(&parse_line(EXAMPLE4)), 92);
+        assert_eq!(part1_best_pair_in_line(&bank(EXAMPLE4)), Ok(92));
     }
 
     #[test]
     fn part2_example1() {
-        assert_eq!(part2_best_joltage(&parse_line(EXAMPLE1)), 987654321111);
+        assert_eq!(part2_best_joltage(&bank(EXAMPLE1)), Ok(987654321111));
     }
 
     #[test]
     fn part2_example2() {
-        assert_eq!(part2_best_joltage(&parse_line(EXAMPLE2)), 811111111119);
+        assert_eq!(part2_best_joltage(&bank(EXAMPLE2)), Ok(811111111119));
     }
 
     #[test]
     fn part2_example3() {
-        assert_eq!(part2_best_joltage(&parse_line(EXAMPLE3)), 434234234278);
+        assert_eq!(part2_best_joltage(&bank(EXAMPLE3)), Ok(434234234278));
     }
 
     #[test]
     fn part2_example4() {
-        assert_eq!(part2_best_joltage(&parse_line(EXAMPLE4)), 888911112111);
+        assert_eq!(part2_best_joltage(&bank(EXAMPLE4)), Ok(888911112111));
+    }
+
+    #[test]
+    fn selected_indices() {
+        let line = bank(EXAMPLE4);
+        let best = best_k_digits(&line, 12).expect("Line is long enough");
+        assert_eq!(best.indices, [0, 2, 4, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+
+        let pair = best_k_digits(&line, 2).expect("Line is long enough");
+        assert_eq!(pair.indices, [6, 11]);
+
+        assert_eq!(
+            best_k_digits(&line, line.len() + 1),
+            Err(SelectionError::TooShort {
+                available: 15,
+                needed: 16
+            })
+        );
+        assert_eq!(
+            best_k_digits(&line, 0),
+            Ok(Selection {
+                value: Joltage::Small(0),
+                indices: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn big_selections() {
+        let line = bank(&"1234567890".repeat(5));
+
+        // Dropping 12345678 then 0123 keeps as many leading nines as possible
+        let small = best_k_digits(&line, 38).expect("Line is long enough");
+        assert_eq!(
+            small.value,
+            Joltage::Small(94567890123456789012345678901234567890)
+        );
+        assert_eq!(small.indices.len(), 38);
+
+        let big = best_k_digits(&line, 40).expect("Line is long enough");
+        assert!(matches!(big.value, Joltage::Big(_)));
+        assert_eq!(
+            big.value.to_string(),
+            "9234567890".to_owned() + &"1234567890".repeat(3)
+        );
+        assert_eq!(big.value.to_u128(), None);
+    }
+
+    #[test]
+    fn smallest_selections() {
+        let line = bank(EXAMPLE3);
+        let worst = worst_k_digits(&line, 4).expect("Line is long enough");
+        assert_eq!(worst.value, Joltage::Small(2222));
+        assert_eq!(worst.indices, [0, 3, 6, 9]);
+
+        let worst = worst_k_digits(&bank("90817"), 3).expect("Line is long enough");
+        assert_eq!(worst.value, Joltage::Small(17));
+    }
+
+    #[test]
+    fn constrained_selections() {
+        let line = bank(EXAMPLE1);
+        let constraints = Constraints {
+            forbidden: vec![0, 2],
+            mandatory: Some(14),
+        };
+        let best = select_k_digits(&line, 3, Goal::Max, &constraints).expect("Batteries left");
+        assert_eq!(best.value, Joltage::Small(861));
+        assert_eq!(best.indices, [1, 3, 14]);
+
+        let forbidden_mandatory = Constraints {
+            forbidden: vec![3],
+            mandatory: Some(3),
+        };
+        assert_eq!(
+            select_k_digits(&line, 2, Goal::Max, &forbidden_mandatory),
+            Err(SelectionError::Unavailable { index: 3 })
+        );
+    }
+
+    #[test]
+    fn selections_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2025);
+
+        for _ in 0..500 {
+            let line = (0..rng.random_range(0..10))
+                .map(|_| rng.random_range(0..=9))
+                .collect_vec();
+            let k = rng.random_range(0..=line.len() + 1);
+            let goal = if rng.random_bool(0.5) {
+                Goal::Max
+            } else {
+                Goal::Min
+            };
+            let constraints = Constraints {
+                forbidden: (0..line.len()).filter(|_| rng.random_bool(0.2)).collect(),
+                mandatory: (!line.is_empty() && rng.random_bool(0.5))
+                    .then(|| rng.random_range(0..line.len())),
+            };
+
+            let selection = select_k_digits(&line, k, goal, &constraints);
+            let digits = selection.as_ref().ok().map(|selection| {
+                let indices = &selection.indices;
+                assert!(indices.iter().tuple_windows().all(|(a, b)| a < b));
+                assert!(indices.iter().all(|i| !constraints.forbidden.contains(i)));
+                assert!(constraints.mandatory.is_none_or(|m| indices.contains(&m)));
+
+                let digits = indices.iter().map(|&i| line[i]).collect_vec();
+                assert_eq!(
+                    selection.value,
+                    Joltage::from_digits(digits.iter().copied())
+                );
+                digits
+            });
+
+            assert_eq!(
+                digits,
+                brute_force(&line, k, goal, &constraints),
+                "{:?}, k = {}, {:?}, {:?}",
+                line,
+                k,
+                goal,
+                constraints
+            );
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse("987\n  81x1\n").expect_err("Parsed a letter");
+        assert_eq!(
+            err.to_string(),
+            "day 3, line 2, column 5: unexpected \"x\", expected a digit"
+        );
+
+        let err = parse("12é4").expect_err("Parsed an accent");
+        assert_eq!(
+            err.to_string(),
+            "day 3, line 1, column 3: unexpected \"é\", expected a digit"
+        );
+    }
+
+    #[test]
+    fn short_banks() {
+        let input = parse("987654321111111\n9").expect("Could not parse");
+        assert_eq!(
+            part1(&input),
+            Err(SelectionError::TooShort {
+                available: 1,
+                needed: 2
+            })
+        );
+        assert_eq!(
+            part2(&input).map_err(|err| err.to_string()),
+            Err("cannot select 12 batteries out of 1".to_owned())
+        );
     }
 }
@@ -1,31 +1,134 @@
-use std::fmt::{self, Write};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write},
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 use itertools::Itertools;
+use ndarray::{Array, Array2, Array3, Axis, Dim, Dimension, IntoDimension, Ix2};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Position(isize, isize);
+use crate::{
+    grid::{self, Grid, GridOptions},
+    image::{self, IndexedImage, Rgb},
+    input::{self, ParseError},
+};
 
-impl std::ops::Add<Position> for Position {
-    type Output = Position;
+#[cfg(test)]
+mod random;
+
+/// Tiles of a warehouse with `N` axes: a grid of rows and columns for 2, a stack of grids for 3
+pub type Warehouse<const N: usize> = Array<Tile, Dim<[usize; N]>>;
+
+/// Which tiles count as the neighbours of a tile, on a warehouse with `N` axes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Neighbourhood<const N: usize = 2> {
+    offsets: Vec<[isize; N]>,
+    /// Whether the warehouse wraps around like a torus instead of stopping at its edges
+    wrap: bool,
+}
 
-    fn add(self, rhs: Position) -> Self::Output {
-        Self(self.0 + rhs.0, self.1 + rhs.1)
+impl<const N: usize> Neighbourhood<N> {
+    fn from_offsets(offsets: impl IntoIterator<Item = [isize; N]>) -> Self {
+        Self {
+            offsets: offsets.into_iter().collect(),
+            wrap: false,
+        }
+    }
+
+    /// The tiles sharing a side: 4 on a grid, 6 in a stack
+    pub fn von_neumann() -> Self {
+        Self::from_offsets((0..N).flat_map(|axis| {
+            [-1, 1].map(|step| {
+                let mut offset = [0; N];
+                offset[axis] = step;
+                offset
+            })
+        }))
+    }
+
+    /// The tiles sharing a side or a corner: 8 on a grid, 26 in a stack
+    pub fn moore() -> Self {
+        Self::radius(1)
+    }
+
+    /// Every tile of the square (or cube) of side `2 * radius + 1` around the tile
+    pub fn radius(radius: usize) -> Self {
+        let radius = radius as isize;
+        Self::from_offsets(
+            (0..N)
+                .map(|_| -radius..=radius)
+                .multi_cartesian_product()
+                .filter(|offset| offset.iter().any(|&step| step != 0))
+                .map(|offset| offset.try_into().expect("One step per axis")),
+        )
+    }
+
+    /// Same neighbourhood with the edges of the warehouse wrapping around, a tile reached through
+    /// several offsets (on warehouses smaller than the neighbourhood) counts once per offset
+    pub fn wrapping(self) -> Self {
+        Self { wrap: true, ..self }
+    }
+
+    /// Neighbours of `position` that are inside a warehouse of size `dim`
+    fn neighbours(
+        &self,
+        dim: Dim<[usize; N]>,
+        position: Dim<[usize; N]>,
+    ) -> impl Iterator<Item = Dim<[usize; N]>> + '_
+    where
+        Dim<[usize; N]>: Dimension,
+    {
+        self.offsets.iter().filter_map(move |offset| {
+            let mut neighbour = position;
+            for ((index, &size), &step) in neighbour
+                .slice_mut()
+                .iter_mut()
+                .zip(dim.slice())
+                .zip(offset)
+            {
+                let (moved, size) = (*index as isize + step, size as isize);
+                *index = if self.wrap {
+                    moved.rem_euclid(size) as usize
+                } else if (0..size).contains(&moved) {
+                    moved as usize
+                } else {
+                    return None;
+                };
+            }
+
+            Some(neighbour)
+        })
     }
 }
 
-const ALL_NEIGHBOURS: [Position; 8] = [
-    Position(-1, -1),
-    Position(-1, 0),
-    Position(-1, 1),
-    Position(0, -1),
-    Position(0, 1),
-    Position(1, -1),
-    Position(1, 0),
-    Position(1, 1),
-];
+impl Neighbourhood<2> {
+    /// The 6 tiles around a hexagon, every row being shifted half a tile to the right of the
+    /// previous one
+    pub fn hexagonal() -> Self {
+        Self::from_offsets([[-1, 0], [-1, 1], [0, -1], [0, 1], [1, -1], [1, 0]])
+    }
+}
+
+/// When a roll can be accessed: it has fewer than `threshold` rolls in its neighbourhood
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rules<const N: usize = 2> {
+    pub neighbourhood: Neighbourhood<N>,
+    pub threshold: usize,
+}
+
+/// Fewer than 4 rolls among the tiles sharing a side or a corner, in a stack like on a grid
+impl<const N: usize> Default for Rules<N> {
+    fn default() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::moore(),
+            threshold: 4,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
-enum Tile {
+pub enum Tile {
     #[default]
     Free,
     Roll,
@@ -41,103 +144,519 @@ impl fmt::Display for Tile {
 }
 
 #[aoc_generator(day04)]
-fn parse(input: &str) -> ndarray::Array2<Tile> {
-    let arrays = input
-        .lines()
-        .map(str::trim)
-        .filter(|line| !line.is_empty())
-        .map(|line| {
-            line.as_bytes()
-                .iter()
-                .map(|&b| match b {
-                    b'.' => Tile::Free,
-                    b'@' => Tile::Roll,
-                    _ => panic!("Byte {:?} is neither '.' nor '@'", b as char),
-                })
-                .collect_vec()
-        })
-        .collect_vec();
+fn parse(input: &str) -> Result<Array2<Tile>, ParseError> {
+    let grid = Grid::parse_with(4, input, GridOptions::default(), |b, _| match b {
+        b'.' => Ok(Tile::Free),
+        b'@' => Ok(Tile::Roll),
+        _ => Err("'.' or '@'"),
+    })?;
 
-    let height = arrays.len();
-    let len = arrays[0].len();
-    let array = arrays
+    Ok(grid.into_array())
+}
+
+fn count_rolls<const N: usize>(
+    grid: &Warehouse<N>,
+    rules: &Rules<N>,
+    position: Dim<[usize; N]>,
+) -> usize
+where
+    Dim<[usize; N]>: Dimension,
+{
+    rules
+        .neighbourhood
+        .neighbours(grid.raw_dim(), position)
+        .filter(|&neighbour| grid[neighbour] == Tile::Roll)
+        .count()
+}
+
+fn part1_check_roll<const N: usize>(
+    grid: &Warehouse<N>,
+    rules: &Rules<N>,
+    roll_position: Dim<[usize; N]>,
+) -> bool
+where
+    Dim<[usize; N]>: Dimension,
+{
+    grid[roll_position] == Tile::Roll && count_rolls(grid, rules, roll_position) < rules.threshold
+}
+
+/// Number of rolls that can be accessed right away
+pub fn accessible_count<const N: usize>(input: &Warehouse<N>, rules: &Rules<N>) -> usize
+where
+    Dim<[usize; N]>: Dimension,
+{
+    ndarray::indices(input.raw_dim())
         .into_iter()
-        .inspect(|line| {
-            if line.len() != len {
-                panic!("Lines with differing length in input");
+        .map(IntoDimension::into_dimension)
+        .filter(|&position| part1_check_roll(input, rules, position))
+        .count()
+}
+
+#[aoc(day04, part1)]
+fn part1(input: &Array2<Tile>) -> usize {
+    accessible_count(input, &Rules::default())
+}
+
+/// Removes accessible rolls by rescanning the whole warehouse until nothing changes
+pub fn removable_rolls_rescan<const N: usize>(input: &Warehouse<N>, rules: &Rules<N>) -> usize
+where
+    Dim<[usize; N]>: Dimension,
+{
+    let mut grid = input.clone();
+    let mut removed = 0;
+    let mut has_removed = true;
+
+    while has_removed {
+        has_removed = false;
+
+        for position in ndarray::indices(grid.raw_dim()) {
+            let position = position.into_dimension();
+            if part1_check_roll(&grid, rules, position) {
+                has_removed = true;
+                removed += 1;
+                grid[position] = Tile::Free;
             }
+        }
+    }
+
+    removed
+}
+
+/// Number of rolls around every tile
+fn neighbour_counts<const N: usize>(
+    grid: &Warehouse<N>,
+    rules: &Rules<N>,
+) -> Array<usize, Dim<[usize; N]>>
+where
+    Dim<[usize; N]>: Dimension,
+{
+    Array::from_shape_fn(grid.raw_dim(), |position| {
+        count_rolls(grid, rules, position.into_dimension())
+    })
+}
+
+/// Rolls accessible right away, marked in `queued`
+fn accessible_rolls<const N: usize>(
+    grid: &Warehouse<N>,
+    rules: &Rules<N>,
+    counts: &Array<usize, Dim<[usize; N]>>,
+    queued: &mut Array<bool, Dim<[usize; N]>>,
+) -> Vec<Dim<[usize; N]>>
+where
+    Dim<[usize; N]>: Dimension,
+{
+    grid.indexed_iter()
+        .map(|(position, &tile)| (position.into_dimension(), tile))
+        .filter(|&(position, tile)| tile == Tile::Roll && counts[position] < rules.threshold)
+        .map(|(position, _)| {
+            queued[position] = true;
+            position
         })
-        .flatten()
-        .collect_vec();
+        .collect()
+}
 
-    ndarray::Array2::<Tile>::from_shape_vec((height, len), array).expect("Could not create ndarray")
+/// Removes the roll at `position`, then queues through `queue_roll` the neighbours this made
+/// accessible
+fn remove_and_update<const N: usize>(
+    grid: &mut Warehouse<N>,
+    rules: &Rules<N>,
+    counts: &mut Array<usize, Dim<[usize; N]>>,
+    queued: &mut Array<bool, Dim<[usize; N]>>,
+    position: Dim<[usize; N]>,
+    mut queue_roll: impl FnMut(Dim<[usize; N]>),
+) where
+    Dim<[usize; N]>: Dimension,
+{
+    grid[position] = Tile::Free;
+
+    for neighbour in rules.neighbourhood.neighbours(grid.raw_dim(), position) {
+        if grid[neighbour] != Tile::Roll {
+            continue;
+        }
+
+        counts[neighbour] -= 1;
+        if counts[neighbour] < rules.threshold && !queued[neighbour] {
+            queued[neighbour] = true;
+            queue_roll(neighbour);
+        }
+    }
 }
 
-fn get_position(grid: &ndarray::Array2<Tile>, position: Position) -> Tile {
-    if position.0 < 0 || position.1 < 0 {
-        return Tile::Free;
+/// Removes accessible rolls with a worklist: neighbour counts are computed once, removing a roll
+/// only decrements the counts around it and queues the neighbours that became accessible
+///
+/// Counts only ever go down so a queued roll stays accessible until it is removed
+pub fn removable_rolls<const N: usize>(input: &Warehouse<N>, rules: &Rules<N>) -> usize
+where
+    Dim<[usize; N]>: Dimension,
+{
+    let mut grid = input.clone();
+    let mut counts = neighbour_counts(input, rules);
+    let mut queued = input.mapv(|_| false);
+    let mut queue: VecDeque<_> = accessible_rolls(input, rules, &counts, &mut queued).into();
+
+    let mut removed = 0;
+    while let Some(position) = queue.pop_front() {
+        remove_and_update(
+            &mut grid,
+            rules,
+            &mut counts,
+            &mut queued,
+            position,
+            |roll| queue.push_back(roll),
+        );
+        removed += 1;
     }
 
-    grid.get((position.0 as usize, position.1 as usize))
-        .copied()
-        .unwrap_or_default()
+    removed
 }
 
-fn remove_roll(grid: &mut ndarray::Array2<Tile>, position: Position) {
-    grid[(position.0 as usize, position.1 as usize)] = Tile::Free;
+/// Rolls removed round by round, along with the grid once nothing else can be removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal {
+    /// Every roll of a wave is accessible once the previous waves are gone, in reading order
+    pub waves: Vec<Vec<grid::Position>>,
+    pub grid: Array2<Tile>,
 }
 
-fn part1_check_roll(grid: &ndarray::Array2<Tile>, roll_position: Position) -> bool {
-    if get_position(grid, roll_position) != Tile::Roll {
-        return false;
+impl Removal {
+    pub fn removed(&self) -> usize {
+        self.waves.iter().map(Vec::len).sum()
     }
+}
 
-    ALL_NEIGHBOURS
-        .iter()
-        .filter(|&&offset| get_position(grid, roll_position + offset) == Tile::Roll)
-        .count()
-        < 4
+/// Removes every accessible roll at once, then does it again on what is left until nothing is
+/// accessible
+///
+/// A roll joins the next wave as soon as its count drops below the threshold while the current one is
+/// removed: it was not accessible before and counts only go down
+pub fn removal_waves(input: &Array2<Tile>, rules: &Rules) -> Removal {
+    let mut grid = input.clone();
+    let mut counts = neighbour_counts(input, rules);
+    let mut queued = input.mapv(|_| false);
+    let mut wave = accessible_rolls(input, rules, &counts, &mut queued);
+    let mut waves = vec![];
+
+    while !wave.is_empty() {
+        let mut next = vec![];
+        for &position in &wave {
+            remove_and_update(
+                &mut grid,
+                rules,
+                &mut counts,
+                &mut queued,
+                position,
+                |roll| next.push(roll),
+            );
+        }
+
+        next.sort_unstable_by_key(|position| position.into_pattern());
+        waves.push(wave.into_iter().map(Ix2::into_pattern).collect());
+        wave = next;
+    }
+
+    Removal { waves, grid }
 }
 
-#[aoc(day04, part1)]
-fn part1(input: &ndarray::Array2<Tile>) -> usize {
-    let mut count = 0;
-    for i in 0..input.shape()[0] as isize {
-        for j in 0..input.shape()[1] as isize {
-            if part1_check_roll(input, Position(i, j)) {
-                count += 1;
+#[aoc(day04, part2)]
+fn part2(input: &Array2<Tile>) -> usize {
+    removable_rolls(input, &Rules::default())
+}
+
+/// Rolls packed one bit per tile, bit `j % 64` of word `j / 64` of a row is column `j`
+///
+/// Every row has an extra free word on each side and there is an extra free row above and below
+/// the grid, so the neighbours of a word are always there
+///
+/// Only knows about the default rules: a roll is accessible with fewer than 4 of its 8 neighbours
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitGrid {
+    rows: usize,
+    cols: usize,
+    stride: usize,
+    bits: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn from_tiles(grid: &Array2<Tile>) -> Self {
+        let (rows, cols) = grid.dim();
+        let stride = cols.div_ceil(u64::BITS as usize) + 2;
+        let mut bits = vec![0; (rows + 2) * stride];
+
+        for ((i, j), &tile) in grid.indexed_iter() {
+            if tile == Tile::Roll {
+                bits[(i + 1) * stride + j / 64 + 1] |= 1 << (j % 64);
             }
         }
+
+        Self {
+            rows,
+            cols,
+            stride,
+            bits,
+        }
     }
 
-    count
+    pub fn to_tiles(&self) -> Array2<Tile> {
+        Array2::from_shape_fn((self.rows, self.cols), |(i, j)| {
+            if self.bits[(i + 1) * self.stride + j / 64 + 1] >> (j % 64) & 1 != 0 {
+                Tile::Roll
+            } else {
+                Tile::Free
+            }
+        })
+    }
+
+    pub fn rolls(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Index of every word that is part of the grid, in reading order
+    fn words(&self) -> impl Iterator<Item = usize> + use<> {
+        let (rows, stride) = (self.rows, self.stride);
+        (1..=rows).flat_map(move |row| (row * stride + 1)..((row + 1) * stride - 1))
+    }
+
+    /// Accessible rolls of the word at `idx`: the 8 neighbour masks are added up bit by bit into
+    /// a counter per tile, its last bit sticks once the count reaches 4
+    fn accessible_word(&self, idx: usize) -> u64 {
+        let (mut ones, mut twos, mut fours) = (0u64, 0u64, 0u64);
+        let mut add = |mask: u64| {
+            let carry = ones & mask;
+            ones ^= mask;
+            fours |= twos & carry;
+            twos ^= carry;
+        };
+
+        for row in [idx - self.stride, idx, idx + self.stride] {
+            let [previous, current, next] =
+                [self.bits[row - 1], self.bits[row], self.bits[row + 1]];
+
+            add(current << 1 | previous >> 63);
+            add(current >> 1 | next << 63);
+            if row != idx {
+                add(current);
+            }
+        }
+
+        self.bits[idx] & !fours
+    }
+
+    pub fn accessible_count(&self) -> usize {
+        self.words()
+            .map(|idx| self.accessible_word(idx).count_ones() as usize)
+            .sum()
+    }
+
+    /// Removes accessible rolls until none is left, with a worklist of words: a word is only
+    /// looked at again when a word around it changed
+    ///
+    /// Rolls only ever become accessible so the order of the removals does not change the result
+    pub fn remove_accessible(&mut self) -> usize {
+        let mut queue: VecDeque<usize> = self.words().collect();
+        let mut queued = vec![false; self.bits.len()];
+        queue.iter().for_each(|&idx| queued[idx] = true);
+
+        let mut removed = 0;
+        while let Some(idx) = queue.pop_front() {
+            queued[idx] = false;
+            // Removals inside a word free the rolls next to them, which can go right away
+            let before = self.bits[idx];
+            loop {
+                let mask = self.accessible_word(idx);
+                if mask == 0 {
+                    break;
+                }
+                self.bits[idx] &= !mask;
+            }
+
+            if self.bits[idx] == before {
+                continue;
+            }
+            removed += (before ^ self.bits[idx]).count_ones() as usize;
+
+            for row in [idx - self.stride, idx, idx + self.stride] {
+                for neighbour in [row - 1, row, row + 1] {
+                    // Only the words of the grid have rolls, the padding is never queued
+                    if !queued[neighbour] && self.bits[neighbour] != 0 {
+                        queued[neighbour] = true;
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+
+        removed
+    }
 }
 
-#[aoc(day04, part2)]
-fn part2(input: &ndarray::Array2<Tile>) -> usize {
-    let mut grid = input.clone();
-    let mut removed = 0;
-    let mut has_removed = true;
+/// Colours of the frames: free tiles, then rolls by neighbour count (0 to 8 or more), then the
+/// rolls removed during the round
+pub const PALETTE: [Rgb; 11] = [
+    [24, 24, 32],
+    [255, 244, 176],
+    [250, 226, 140],
+    [243, 204, 105],
+    [232, 178, 76],
+    [214, 149, 55],
+    [189, 119, 41],
+    [160, 91, 32],
+    [128, 66, 26],
+    [96, 46, 21],
+    [230, 40, 60],
+];
 
-    while has_removed {
-        has_removed = false;
+const REMOVED_COLOUR: u8 = PALETTE.len() as u8 - 1;
+
+/// The grid at the start of a removal round
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    tiles: Array2<Tile>,
+    counts: Array2<usize>,
+    removed: Array2<bool>,
+}
 
-        for i in 0..grid.shape()[0] as isize {
-            for j in 0..grid.shape()[1] as isize {
-                if part1_check_roll(&grid, Position(i, j)) {
-                    has_removed = true;
-                    removed += 1;
-                    remove_roll(&mut grid, Position(i, j));
+impl Frame {
+    fn colour(&self, position: (usize, usize)) -> u8 {
+        match self.tiles[position] {
+            Tile::Free => 0,
+            Tile::Roll if self.removed[position] => REMOVED_COLOUR,
+            Tile::Roll => 1 + self.counts[position].min(8) as u8,
+        }
+    }
+
+    /// Indices into `PALETTE`, every tile is `scale` pixels wide
+    pub fn image(&self, scale: usize) -> IndexedImage {
+        let (rows, cols) = self.tiles.dim();
+        IndexedImage::from_fn(cols, rows, |row, col| self.colour((row, col))).scaled(scale)
+    }
+}
+
+/// Rolls removed during the round are shown as `x`
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.tiles.nrows() {
+            for col in 0..self.tiles.ncols() {
+                if self.removed[(row, col)] {
+                    f.write_char('x')?;
+                } else {
+                    write!(f, "{}", self.tiles[(row, col)])?;
                 }
             }
+            f.write_char('\n')?;
         }
+
+        Ok(())
     }
+}
 
-    removed
+/// One frame per removal wave, plus the stable grid left at the end
+pub fn removal_frames(input: &Array2<Tile>, rules: &Rules) -> Vec<Frame> {
+    let waves = removal_waves(input, rules).waves;
+    let mut tiles = input.clone();
+    let mut frames = Vec::with_capacity(waves.len() + 1);
+
+    for wave in waves.iter().map(Some).chain([None]) {
+        let mut removed = input.mapv(|_| false);
+        wave.into_iter()
+            .flatten()
+            .for_each(|&pos| removed[pos] = true);
+
+        frames.push(Frame {
+            counts: neighbour_counts(&tiles, rules),
+            tiles: tiles.clone(),
+            removed,
+        });
+
+        for &pos in wave.into_iter().flatten() {
+            tiles[pos] = Tile::Free;
+        }
+    }
+
+    frames
+}
+
+/// Writes the frames to `frame_000.ppm`, `frame_001.ppm`... in `dir`
+pub fn write_ppm_frames(frames: &[Frame], dir: &Path, scale: usize) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+
+    frames
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            let path = dir.join(format!("frame_{:03}.ppm", i));
+            fs::write(&path, image::ppm(&frame.image(scale), &PALETTE))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Writes the frames as an animated GIF, showing each for `delay` hundredths of a second
+pub fn write_gif(frames: &[Frame], path: &Path, scale: usize, delay: u16) -> io::Result<()> {
+    let images = frames
+        .iter()
+        .map(|frame| frame.image(scale))
+        .collect::<Vec<_>>();
+    fs::write(path, image::gif(&images, &PALETTE, delay))
+}
+
+/// Parses layers of rolls separated by blank lines, from the bottom one to the top one
+pub fn parse_layers(input: &str) -> Result<Array3<Tile>, ParseError> {
+    let mut layers: Vec<Array2<Tile>> = vec![];
+    let lines = input::all_lines(4, input).collect_vec();
+
+    for group in lines.split(|line| line.text.is_empty()) {
+        let Some(first) = group.first() else {
+            continue;
+        };
+
+        let layer = Grid::parse_lines_with(
+            4,
+            group.iter().copied(),
+            GridOptions::default(),
+            |b, _| match b {
+                b'.' => Ok(Tile::Free),
+                b'@' => Ok(Tile::Roll),
+                _ => Err("'.' or '@'"),
+            },
+        )?
+        .into_array();
+
+        if let Some(previous) = layers.last()
+            && previous.dim() != layer.dim()
+        {
+            return Err(first.invalid(
+                first.text,
+                format!(
+                    "layer is {}x{} but previous layers are {}x{}",
+                    layer.nrows(),
+                    layer.ncols(),
+                    previous.nrows(),
+                    previous.ncols()
+                ),
+            ));
+        }
+
+        layers.push(layer);
+    }
+
+    if layers.is_empty() {
+        return Err(ParseError::missing(4, "the first layer"));
+    }
+
+    let views = layers.iter().map(|layer| layer.view()).collect_vec();
+    Ok(ndarray::stack(Axis(0), &views).expect("Layers all have the same size"))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::ops::RangeInclusive;
+
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
     use super::*;
 
     const EXAMPLE: &str = "
@@ -153,13 +672,327 @@ mod tests {
         @.@.@@@.@.
     ";
 
+    #[test]
+    fn parse_errors() {
+        let err = parse("é@.\n@@@").expect_err("Parsed an accent");
+        assert_eq!(
+            err.to_string(),
+            "day 4, line 1, column 1: unexpected \"é\", expected '.' or '@'"
+        );
+    }
+
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), 13);
+        assert_eq!(part1(&parse(EXAMPLE).expect("Could not parse")), 13);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), 43);
+        assert_eq!(part2(&parse(EXAMPLE).expect("Could not parse")), 43);
+    }
+
+    /// Grid (or stack) with a side drawn from each range and a random density
+    fn random_grid<const N: usize>(
+        rng: &mut StdRng,
+        sides: [RangeInclusive<usize>; N],
+    ) -> Warehouse<N>
+    where
+        Dim<[usize; N]>: Dimension,
+    {
+        let mut shape = Dim::<[usize; N]>::zeros(N);
+        for (side, range) in shape.slice_mut().iter_mut().zip(sides) {
+            *side = rng.random_range(range);
+        }
+
+        let density = rng.random_range(0.3..0.9);
+        random::random_warehouse(rng, shape, density)
+    }
+
+    #[test]
+    fn part2_matches_rescan() {
+        let mut rng = StdRng::seed_from_u64(2025);
+        let neighbourhoods = [
+            Neighbourhood::moore(),
+            Neighbourhood::von_neumann(),
+            Neighbourhood::radius(2),
+            Neighbourhood::hexagonal(),
+            Neighbourhood::moore().wrapping(),
+            Neighbourhood::hexagonal().wrapping(),
+        ];
+
+        for i in 0..120 {
+            let grid = random_grid(&mut rng, [1..=30, 1..=30]);
+            let neighbourhood = neighbourhoods[i % neighbourhoods.len()].clone();
+            let threshold = rng.random_range(1..=neighbourhood.offsets.len());
+            let rules = Rules {
+                neighbourhood,
+                threshold,
+            };
+
+            let removed = removable_rolls_rescan(&grid, &rules);
+            assert_eq!(removable_rolls(&grid, &rules), removed, "{:?}", rules);
+            assert_eq!(removal_waves(&grid, &rules).removed(), removed);
+        }
+    }
+
+    #[test]
+    fn bit_grid_matches_tiles() {
+        let mut rng = StdRng::seed_from_u64(2025);
+        let rules = Rules::default();
+
+        for _ in 0..60 {
+            let grid = random_grid(&mut rng, [1..=40, 1..=200]);
+
+            let mut bits = BitGrid::from_tiles(&grid);
+            assert_eq!(bits.to_tiles(), grid);
+            assert_eq!(bits.accessible_count(), accessible_count(&grid, &rules));
+
+            let removal = removal_waves(&grid, &rules);
+            assert_eq!(bits.remove_accessible(), removal.removed());
+            assert_eq!(bits.to_tiles(), removal.grid);
+        }
+    }
+
+    #[test]
+    fn bit_grid_example() {
+        let mut bits = BitGrid::from_tiles(&parse(EXAMPLE).expect("Could not parse"));
+        assert_eq!(bits.rolls(), 71);
+        assert_eq!(bits.accessible_count(), 13);
+        assert_eq!(bits.remove_accessible(), 43);
+        assert_eq!(bits.rolls(), 71 - 43);
+    }
+
+    #[test]
+    fn neighbourhoods() {
+        let sizes = [
+            Neighbourhood::von_neumann(),
+            Neighbourhood::moore(),
+            Neighbourhood::radius(2),
+            Neighbourhood::hexagonal(),
+        ]
+        .map(|neighbourhood| neighbourhood.offsets.len());
+        assert_eq!(sizes, [4, 8, 24, 6]);
+
+        let sizes = [
+            Neighbourhood::<3>::von_neumann(),
+            Neighbourhood::moore(),
+            Neighbourhood::radius(2),
+        ]
+        .map(|neighbourhood| neighbourhood.offsets.len());
+        assert_eq!(sizes, [6, 26, 124]);
+
+        let corner = |neighbourhood: &Neighbourhood| {
+            let mut neighbours = neighbourhood
+                .neighbours(Dim([3, 4]), Dim([0, 0]))
+                .map(Ix2::into_pattern)
+                .collect::<Vec<_>>();
+            neighbours.sort_unstable();
+            neighbours
+        };
+        assert_eq!(corner(&Neighbourhood::moore()), [(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(
+            corner(&Neighbourhood::von_neumann().wrapping()),
+            [(0, 1), (0, 3), (1, 0), (2, 0)]
+        );
+        assert_eq!(
+            corner(&Neighbourhood::hexagonal().wrapping()),
+            [(0, 1), (0, 3), (1, 0), (1, 3), (2, 0), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn other_rules() {
+        let grid = parse(EXAMPLE).expect("Could not parse");
+        let accessible = |neighbourhood, threshold| {
+            accessible_count(
+                &grid,
+                &Rules {
+                    neighbourhood,
+                    threshold,
+                },
+            )
+        };
+
+        assert_eq!(accessible(Neighbourhood::moore(), 4), 13);
+        // Only rolls surrounded on every side by rolls stay
+        assert_eq!(accessible(Neighbourhood::von_neumann(), 4), 71 - 13);
+        // A full grid on a torus has no edge to start from
+        let full = Array2::from_elem((5, 5), Tile::Roll);
+        let torus = Rules {
+            neighbourhood: Neighbourhood::moore().wrapping(),
+            threshold: 8,
+        };
+        assert_eq!(accessible_count(&full, &torus), 0);
+        // Without wrapping only the corners go, the tiles next to them keep 4 neighbours
+        assert_eq!(removable_rolls(&full, &Rules::default()), 4);
+    }
+
+    #[test]
+    fn part2_example_waves() {
+        let removal = removal_waves(&parse(EXAMPLE).expect("Could not parse"), &Rules::default());
+        let sizes = removal.waves.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(sizes, [13, 12, 7, 5, 2, 1, 1, 1, 1]);
+        assert_eq!(removal.removed(), 43);
+        assert_eq!(removal.waves[0][..3], [(0, 2), (0, 3), (0, 5)]);
+
+        let stable = parse(
+            "
+            ..........
+            ..........
+            ..........
+            ....@@....
+            ...@@@@...
+            ...@@@@@..
+            ...@.@.@@.
+            ...@@.@@@.
+            ...@@@@@..
+            ....@@@...
+        ",
+        )
+        .expect("Could not parse");
+        assert_eq!(removal.grid, stable);
+    }
+
+    #[test]
+    fn removal_frames_example() {
+        let grid = parse(EXAMPLE).expect("Could not parse");
+        let frames = removal_frames(&grid, &Rules::default());
+        assert_eq!(frames.len(), 10);
+
+        assert_eq!(
+            frames[0].to_string().lines().take(3).collect::<Vec<_>>(),
+            ["..xx.xx@x.", "x@@.@.@.@@", "@@@@@.x.@@"]
+        );
+        assert!(!frames[9].to_string().contains('x'));
+
+        let image = frames[0].image(2);
+        assert_eq!((image.width, image.height), (20, 20));
+        // (0, 8) has 3 neighbours but is removed, (0, 9) is free and (1, 1) has 6 neighbours
+        assert_eq!(image.pixels[16..20], [REMOVED_COLOUR, REMOVED_COLOUR, 0, 0]);
+        assert_eq!(image.pixels[2 * 20 + 2], 7);
+    }
+
+    #[test]
+    fn removal_files() {
+        let grid = parse(EXAMPLE).expect("Could not parse");
+        let frames = removal_frames(&grid, &Rules::default());
+        let dir = std::env::temp_dir().join(format!("aoc-2025-day04-{}", std::process::id()));
+
+        let paths = write_ppm_frames(&frames, &dir, 3).expect("Could not write frames");
+        assert_eq!(paths.len(), frames.len());
+        let first = fs::read(&paths[0]).expect("Could not read frame");
+        assert!(first.starts_with(b"P6\n30 30\n255\n"));
+        assert_eq!(first.len(), 13 + 30 * 30 * 3);
+
+        let gif = dir.join("removal.gif");
+        write_gif(&frames, &gif, 3, 20).expect("Could not write gif");
+        assert!(
+            fs::read(&gif)
+                .expect("Could not read gif")
+                .starts_with(b"GIF89a")
+        );
+
+        fs::remove_dir_all(&dir).expect("Could not clean up");
+    }
+
+    #[test]
+    fn single_layer_stack() {
+        let stack = parse_layers(EXAMPLE).expect("Could not parse");
+        assert_eq!(stack.dim(), (1, 10, 10));
+
+        let rules = Rules::default();
+        assert_eq!(accessible_count(&stack, &rules), 13);
+        assert_eq!(removable_rolls(&stack, &rules), 43);
+    }
+
+    #[test]
+    fn stacked_layers() {
+        let stack = parse_layers(
+            "
+            @@@
+            @@@
+            @@@
+
+            @@@
+            @.@
+            @@@
+
+            @@@
+            @@@
+            @@@
+        ",
+        )
+        .expect("Could not parse");
+        assert_eq!(stack.dim(), (3, 3, 3));
+
+        // Corners have 6 neighbours, edges 10 and the centres of the faces 16 until the corners
+        // and edges are gone
+        let rules = Rules {
+            neighbourhood: Neighbourhood::moore(),
+            threshold: 13,
+        };
+        assert_eq!(accessible_count(&stack, &rules), 8 + 12);
+        assert_eq!(removable_rolls(&stack, &rules), 26);
+
+        // Every roll shares a face with at least 3 others
+        let faces = Rules {
+            neighbourhood: Neighbourhood::von_neumann(),
+            threshold: 3,
+        };
+        assert_eq!(accessible_count(&stack, &faces), 0);
+        assert_eq!(accessible_count(&stack, &Rules::default()), 0);
+        // Wrapping around, every other tile is a neighbour so every roll sees 25 rolls
+        let torus = Rules {
+            neighbourhood: Neighbourhood::moore().wrapping(),
+            threshold: 26,
+        };
+        assert_eq!(accessible_count(&stack, &torus), 26);
+    }
+
+    #[test]
+    fn stacks_match_rescan() {
+        let mut rng = StdRng::seed_from_u64(2025);
+        let neighbourhoods = [
+            Neighbourhood::moore(),
+            Neighbourhood::von_neumann(),
+            Neighbourhood::moore().wrapping(),
+        ];
+
+        for i in 0..40 {
+            let stack = random_grid(&mut rng, [1..=6, 1..=8, 1..=8]);
+            let neighbourhood = neighbourhoods[i % neighbourhoods.len()].clone();
+            let threshold = rng.random_range(1..=neighbourhood.offsets.len());
+            let rules = Rules {
+                neighbourhood,
+                threshold,
+            };
+
+            assert_eq!(
+                removable_rolls(&stack, &rules),
+                removable_rolls_rescan(&stack, &rules),
+                "{:?}",
+                rules
+            );
+        }
+    }
+
+    #[test]
+    fn layer_errors() {
+        let err = parse_layers("@@\n@@\n\n@@@\n@@@").expect_err("Parsed layers of different sizes");
+        assert_eq!(
+            err.to_string(),
+            "day 4, line 4, column 1: invalid \"@@@\", layer is 2x3 but previous layers are 2x2"
+        );
+
+        let err = parse_layers("@@\n\n@x").expect_err("Parsed a letter");
+        assert_eq!(
+            err.to_string(),
+            "day 4, line 3, column 2: unexpected \"x\", expected '.' or '@'"
+        );
+
+        assert_eq!(
+            parse_layers("\n\n"),
+            Err(ParseError::missing(4, "the first layer"))
+        );
     }
 }
@@ -0,0 +1,21 @@
+//! Random warehouses, shared by the tests and the benchmarks (which include this file)
+
+use ndarray::{Array, ShapeBuilder};
+use rand::Rng;
+
+use super::Tile;
+
+/// Warehouse of the given shape, every tile being a roll with probability `density`
+pub fn random_warehouse<Sh: ShapeBuilder>(
+    rng: &mut impl Rng,
+    shape: Sh,
+    density: f64,
+) -> Array<Tile, Sh::Dim> {
+    Array::from_shape_simple_fn(shape, || {
+        if rng.random_bool(density) {
+            Tile::Roll
+        } else {
+            Tile::Free
+        }
+    })
+}
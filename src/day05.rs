@@ -1,33 +1,33 @@
-use std::{cmp::Reverse, collections::BinaryHeap};
-
 use itertools::Itertools;
 
+use crate::{
+    input::{self, ParseError},
+    ranges::merge_ranges,
+};
+
+type Database = (Vec<(u64, u64)>, Vec<u64>);
+
 #[aoc_generator(day05)]
-fn parse(input: &str) -> (Vec<(u64, u64)>, Vec<u64>) {
-    let mut iter = input
-        .lines()
-        .map(str::trim)
-        .skip_while(|line| line.is_empty());
+fn parse(input: &str) -> Result<Database, ParseError> {
+    let mut iter = input::all_lines(5, input).skip_while(|line| line.text.is_empty());
 
     let ranges = iter
-        .take_while_ref(|line| !line.is_empty())
+        .take_while_ref(|line| !line.text.is_empty())
         .map(|line| {
-            let (lower, upper) = line.split_once('-').expect("Could not split range");
+            let Some((lower, upper)) = line.text.split_once('-') else {
+                return Err(line.unexpected(line.text, "a range like '3-5'"));
+            };
 
-            (
-                lower.parse().expect("Could not parse number"),
-                upper.parse().expect("Could not parse number"),
-            )
+            Ok((line.parse(lower)?, line.parse(upper)?))
         })
-        .collect_vec();
+        .try_collect()?;
 
     let ids = iter
-        .filter(|line| !line.is_empty())
-        .map(str::parse)
-        .try_collect()
-        .expect("Could not parse number");
+        .filter(|line| !line.text.is_empty())
+        .map(|line| line.parse(line.text))
+        .try_collect()?;
 
-    (ranges, ids)
+    Ok((ranges, ids))
 }
 
 fn id_in_any_range(ranges: &[(u64, u64)], id: u64) -> bool {
@@ -41,7 +41,7 @@ fn id_in_any_range(ranges: &[(u64, u64)], id: u64) -> bool {
 }
 
 #[aoc(day05, part1)]
-fn part1(input: &(Vec<(u64, u64)>, Vec<u64>)) -> usize {
+fn part1(input: &Database) -> usize {
     input
         .1
         .iter()
@@ -49,31 +49,9 @@ fn part1(input: &(Vec<(u64, u64)>, Vec<u64>)) -> usize {
         .count()
 }
 
-fn optimize_ranges(ranges: &[(u64, u64)]) -> Vec<(u64, u64)> {
-    // BinaryHeap to the rescue
-    let mut heap: BinaryHeap<_> = ranges.iter().copied().map(Reverse).collect();
-    let mut result = vec![];
-
-    while let Some(Reverse((lower, upper))) = heap.pop() {
-        let Some((_, last_upper)) = result.last_mut() else {
-            result.push((lower, upper));
-            continue;
-        };
-
-        if lower - 1 <= *last_upper {
-            *last_upper = upper.max(*last_upper);
-            continue;
-        }
-
-        result.push((lower, upper));
-    }
-
-    result
-}
-
 #[aoc(day05, part2)]
-fn part2(input: &(Vec<(u64, u64)>, Vec<u64>)) -> u64 {
-    optimize_ranges(&input.0)
+fn part2(input: &Database) -> u64 {
+    merge_ranges(&input.0)
         .into_iter()
         // .inspect(|range| print!("{range:?} -> "))
         .map(|(lower, upper)| upper + 1 - lower)
@@ -100,11 +78,11 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), 3);
+        assert_eq!(part1(&parse(EXAMPLE).expect("Could not parse")), 3);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), 14);
+        assert_eq!(part2(&parse(EXAMPLE).expect("Could not parse")), 14);
     }
 }
@@ -1,6 +1,13 @@
+use std::convert::Infallible;
+
 use itertools::{Itertools, zip_eq};
 use ndarray::Axis;
 
+use crate::{
+    grid::{Grid, GridOptions, Padding, Trim},
+    input::{self, InputLine, ParseError},
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Operator {
     Add,
@@ -19,76 +26,69 @@ impl TryFrom<char> for Operator {
     }
 }
 
+fn parse_operators(line: Option<InputLine>) -> Result<ndarray::Array1<Operator>, ParseError> {
+    let line = line.ok_or_else(|| ParseError::missing(6, "the operators line"))?;
+
+    line.raw
+        .char_indices()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, c)| {
+            Operator::try_from(c)
+                .map_err(|_| line.unexpected(&line.raw[i..(i + c.len_utf8())], "'+' or '*'"))
+        })
+        .try_collect()
+}
+
 #[aoc_generator(day06, part1)]
-fn parse_p1(input: &str) -> (ndarray::Array2<u64>, ndarray::Array1<Operator>) {
-    let mut lines = input.lines().map(str::trim).filter(|line| !line.is_empty());
-    let arrays = lines
-        .take_while_ref(|line| line.starts_with(|c: char| c.is_ascii_digit()))
+fn parse_p1(input: &str) -> Result<(ndarray::Array2<u64>, ndarray::Array1<Operator>), ParseError> {
+    let mut lines = input::lines(6, input);
+    let rows: Vec<_> = lines
+        .take_while_ref(|line| line.text.starts_with(|c: char| c.is_ascii_digit()))
         .map(|line| {
-            line.split_whitespace()
-                .map(|num| num.parse::<u64>().expect("Could not parse u64"))
-                .collect_vec()
-        })
-        .collect_vec();
-
-    let operators = lines
-        .next()
-        .expect("No operators line in input")
-        .chars()
-        .map(Operator::try_from)
-        .filter_map(|res| res.ok())
-        .collect();
-
-    let height = arrays.len();
-    let len = arrays[0].len();
-    let array = arrays
-        .into_iter()
-        .inspect(|line| {
-            if line.len() != len {
-                panic!("Lines with differing length in input");
-            }
+            let row = line
+                .text
+                .split_whitespace()
+                .map(|num| line.parse::<u64>(num))
+                .try_collect()?;
+
+            Ok::<_, ParseError>((line, row))
         })
-        .flatten()
-        .collect_vec();
+        .try_collect()?;
+
+    let operators_line = lines.next();
+    let operators = parse_operators(operators_line)?;
+    let grid = Grid::from_rows(6, rows)?;
+
+    if let Some(line) = operators_line
+        && operators.len() != grid.ncols()
+    {
+        return Err(line.invalid(
+            line.text,
+            format!("{} operators for {} columns", operators.len(), grid.ncols()),
+        ));
+    }
 
-    (
-        ndarray::Array2::from_shape_vec((height, len), array).expect("Could not create ndarray"),
-        operators,
-    )
+    Ok((grid.into_array(), operators))
 }
 
 #[aoc_generator(day06, part2)]
-fn parse_p2(input: &str) -> (ndarray::Array2<u8>, ndarray::Array1<Operator>) {
-    let mut lines = input.lines();
-    let arrays = lines
-        .take_while_ref(|line| line.trim_start().starts_with(|c: char| c.is_ascii_digit()))
-        .map(|line| line.as_bytes().to_owned())
-        .collect_vec();
-
-    let operators = lines
-        .next()
-        .expect("No operators line in input")
-        .chars()
-        .map(Operator::try_from)
-        .filter_map(|res| res.ok())
-        .collect();
-
-    let height = arrays.len();
-    let len = arrays[0].len();
-    let array = arrays
-        .into_iter()
-        .inspect(|line| {
-            if line.len() != len {
-                panic!("Lines with differing length in input");
-            }
-        })
-        .flatten()
-        .collect_vec();
-
-    (
-        ndarray::Array2::from_shape_vec((height, len), array).expect("Could not create ndarray"),
-        operators,
-    )
+fn parse_p2(input: &str) -> Result<(ndarray::Array2<u8>, ndarray::Array1<Operator>), ParseError> {
+    let mut lines = input::all_lines(6, input);
+    let options = GridOptions {
+        trim: Trim::None,
+        padding: Padding::With(b' '),
+        skip_empty_lines: false,
+    };
+    let grid = Grid::parse_lines_with(
+        6,
+        lines.take_while_ref(|line| line.text.starts_with(|c: char| c.is_ascii_digit())),
+        options,
+        |b, _| Ok::<_, Infallible>(b),
+    )?;
+
+    let operators = parse_operators(lines.next())?;
+
+    Ok((grid.into_array(), operators))
 }
 
 #[aoc(day06, part1)]
@@ -154,11 +154,29 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_p1(EXAMPLE)), 4277556);
+        assert_eq!(part1(&parse_p1(EXAMPLE).expect("Could not parse")), 4277556);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse_p2(EXAMPLE)), 3263827);
+        assert_eq!(part2(&parse_p2(EXAMPLE).expect("Could not parse")), 3263827);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse_p1("1 2\n3 4\n+ -").expect_err("Parsed '-'");
+        assert_eq!(
+            err.to_string(),
+            "day 6, line 3, column 3: unexpected \"-\", expected '+' or '*'"
+        );
+
+        let err = parse_p1("1 2\n3 4 5\n+ *").expect_err("Parsed differing lengths");
+        assert_eq!(
+            err.to_string(),
+            "day 6, line 2, column 1: invalid \"3 4 5\", row has length 3 but previous rows have length 2"
+        );
+
+        let err = parse_p2("1 2\n3 4").expect_err("Parsed without operators");
+        assert_eq!(err, ParseError::missing(6, "the operators line"));
     }
 }
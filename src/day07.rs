@@ -1,9 +1,11 @@
 use std::fmt::{self, Write};
 
 use fnv::{FnvHashMap, FnvHashSet};
-use itertools::Itertools;
 
-type Position = (usize, usize);
+use crate::{
+    grid::{Grid, GridOptions, Position},
+    input::ParseError,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Tile {
@@ -21,47 +23,20 @@ impl fmt::Display for Tile {
 }
 
 #[aoc_generator(day07)]
-fn parse(input: &str) -> (ndarray::Array2<Tile>, Position) {
-    let mut start = (0, 0);
-    let arrays = input
-        .lines()
-        .map(str::trim)
-        .filter(|line| !line.is_empty())
-        .enumerate()
-        .map(|(row, line)| {
-            line.as_bytes()
-                .iter()
-                .copied()
-                .enumerate()
-                .map(|(col, b)| match b {
-                    b'.' => Tile::Free,
-                    b'^' => Tile::Splitter,
-                    b'S' => {
-                        start = (row, col);
-                        Tile::Free
-                    }
-                    _ => panic!("{:?} was not in ('.', '^', 'S')", b as char),
-                })
-                .collect_vec()
-        })
-        .collect_vec();
-
-    let height = arrays.len();
-    let len = arrays[0].len();
-    let array = arrays
-        .into_iter()
-        .inspect(|line| {
-            if line.len() != len {
-                panic!("Lines with differing length in input");
-            }
-        })
-        .flatten()
-        .collect_vec();
-
-    (
-        ndarray::Array2::from_shape_vec((height, len), array).expect("Could not create ndarray"),
-        start,
-    )
+fn parse(input: &str) -> Result<(ndarray::Array2<Tile>, Position), ParseError> {
+    let (grid, start) = Grid::parse_with_marker(
+        7,
+        input,
+        GridOptions::default(),
+        (b'S', b'.'),
+        |b, _| match b {
+            b'.' => Ok(Tile::Free),
+            b'^' => Ok(Tile::Splitter),
+            _ => Err("'.', '^' or 'S'"),
+        },
+    )?;
+
+    Ok((grid.into_array(), start))
 }
 
 fn part1_logic(
@@ -169,11 +144,11 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), 21);
+        assert_eq!(part1(&parse(EXAMPLE).expect("Could not parse")), 21);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), 40);
+        assert_eq!(part2(&parse(EXAMPLE).expect("Could not parse")), 40);
     }
 }
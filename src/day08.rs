@@ -1,8 +1,10 @@
-use std::{cmp, error::Error, str::FromStr};
+use std::cmp;
 
 use fnv::FnvHashMap;
 use itertools::Itertools;
 
+use crate::input::{self, InputLine, ParseError};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Position {
     x: i64,
@@ -25,26 +27,21 @@ impl Position {
     }
 }
 
-impl FromStr for Position {
-    type Err = Box<dyn Error>;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let Some((x, y, z)) = s.split(',').collect_tuple() else {
-            return Err(format!("Could not split string {:?} 3 times on ','", s).into());
-        };
+fn parse_position(line: InputLine) -> Result<Position, ParseError> {
+    let Some((x, y, z)) = line.text.split(',').collect_tuple() else {
+        return Err(line.unexpected(line.text, "3 coordinates separated by ','"));
+    };
 
-        Ok(Self::new(x.parse()?, y.parse()?, z.parse()?))
-    }
+    Ok(Position::new(
+        line.parse(x)?,
+        line.parse(y)?,
+        line.parse(z)?,
+    ))
 }
 
 #[aoc_generator(day08)]
-fn parse(input: &str) -> Result<Vec<Position>, Box<dyn Error>> {
-    input
-        .lines()
-        .map(str::trim)
-        .filter(|line| !line.is_empty())
-        .map(str::parse)
-        .try_collect()
+fn parse(input: &str) -> Result<Vec<Position>, ParseError> {
+    input::lines(8, input).map(parse_position).try_collect()
 }
 
 fn make_connections(
@@ -1,6 +1,8 @@
 use geo::{Coord, Intersects, Line, Rect};
 use itertools::Itertools;
 
+use crate::input::{self, ParseError};
+
 type Position = Coord<i64>;
 
 fn rect_intersects_no_edge(
@@ -34,26 +36,16 @@ fn rect_intersects_no_edge(
 }
 
 #[aoc_generator(day09)]
-fn parse(input: &str) -> Vec<Position> {
-    input
-        .lines()
-        .map(str::trim)
-        .filter(|line| !line.is_empty())
+fn parse(input: &str) -> Result<Vec<Position>, ParseError> {
+    input::lines(9, input)
         .map(|line| {
-            let (left, right) = line
-                .split_once(',')
-                .unwrap_or_else(|| panic!("Could not parse {:?}", line));
-
-            (
-                left.parse()
-                    .unwrap_or_else(|err| panic!("Could not parse {:?} ({:?})", line, err)),
-                right
-                    .parse()
-                    .unwrap_or_else(|err| panic!("Could not parse {:?} ({:?})", line, err)),
-            )
-                .into()
+            let Some((left, right)) = line.text.split_once(',') else {
+                return Err(line.unexpected(line.text, "2 coordinates separated by ','"));
+            };
+
+            Ok((line.parse(left)?, line.parse(right)?).into())
         })
-        .collect()
+        .try_collect()
 }
 
 #[aoc(day09, part1)]
@@ -115,11 +107,11 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), 50);
+        assert_eq!(part1(&parse(EXAMPLE).expect("Could not parse")), 50);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), 24);
+        assert_eq!(part2(&parse(EXAMPLE).expect("Could not parse")), 24);
     }
 }
@@ -0,0 +1,679 @@
+use itertools::Itertools;
+use ndarray::{Array2, ArrayViewMut1, Zip, s};
+
+use crate::input::{self, InputLine, ParseError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Machine {
+    lights: Vec<bool>,
+    buttons: Vec<Vec<usize>>,
+    joltage: Vec<u64>,
+}
+
+fn strip_delimiters<'a>(
+    line: &InputLine,
+    token: &'a str,
+    open: char,
+    close: char,
+) -> Result<&'a str, ParseError> {
+    token
+        .strip_prefix(open)
+        .and_then(|t| t.strip_suffix(close))
+        .ok_or_else(|| line.unexpected(token, format!("{:?}...{:?}", open, close)))
+}
+
+/// Buttons pressed for the lights are a mask of this many bits
+const MAX_BUTTONS: usize = u64::BITS as usize;
+
+fn parse_machine(line: InputLine) -> Result<Machine, ParseError> {
+    let tokens = line.text.split_whitespace().collect_vec();
+    let [lights, buttons @ .., joltage] = tokens.as_slice() else {
+        return Err(line.unexpected(line.text, "a light diagram and joltages"));
+    };
+
+    if let Some(extra) = buttons.get(MAX_BUTTONS) {
+        return Err(line.invalid(
+            extra,
+            format!("machines have at most {} buttons", MAX_BUTTONS),
+        ));
+    }
+
+    let lights = strip_delimiters(&line, lights, '[', ']')?;
+    let lights: Vec<bool> = lights
+        .char_indices()
+        .map(|(i, c)| match c {
+            '.' => Ok(false),
+            '#' => Ok(true),
+            _ => Err(line.unexpected(&lights[i..(i + c.len_utf8())], "'.' or '#'")),
+        })
+        .try_collect()?;
+
+    let buttons: Vec<Vec<usize>> = buttons
+        .iter()
+        .map(|button| {
+            strip_delimiters(&line, button, '(', ')')?
+                .split(',')
+                .map(|idx| {
+                    let light: usize = line.parse(idx)?;
+                    if light >= lights.len() {
+                        return Err(
+                            line.invalid(idx, format!("there are only {} lights", lights.len()))
+                        );
+                    }
+
+                    Ok(light)
+                })
+                .try_collect()
+        })
+        .try_collect()?;
+
+    let joltage_text = strip_delimiters(&line, joltage, '{', '}')?;
+    let joltage: Vec<u64> = joltage_text
+        .split(',')
+        .map(|j| line.parse(j))
+        .try_collect()?;
+
+    if joltage.len() != lights.len() {
+        return Err(line.invalid(
+            joltage_text,
+            format!(
+                "{} joltage requirements for {} lights",
+                joltage.len(),
+                lights.len()
+            ),
+        ));
+    }
+
+    Ok(Machine {
+        lights,
+        buttons,
+        joltage,
+    })
+}
+
+#[aoc_generator(day10)]
+fn parse(input: &str) -> Result<Vec<Machine>, ParseError> {
+    input::lines(10, input).map(parse_machine).try_collect()
+}
+
+fn swap_rows<T>(matrix: &mut Array2<T>, a: usize, b: usize) {
+    if a == b {
+        return;
+    }
+
+    let (mut a, mut b) = matrix.multi_slice_mut((s![a, ..], s![b, ..]));
+    Zip::from(&mut a).and(&mut b).for_each(std::mem::swap);
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+/// Divides the whole row by the gcd of its coefficients, keeps the numbers small
+fn normalize_row(mut row: ArrayViewMut1<i64>) {
+    let divisor = row.iter().fold(0, |acc, &v| gcd(acc, v.unsigned_abs()));
+    if divisor > 1 {
+        row.mapv_inplace(|v| v / divisor as i64);
+    }
+}
+
+/// Reduces the augmented `matrix` (last column is the right hand side) to its reduced row echelon
+/// form over GF(2), returns the pivot column of each non-zero row
+fn gf2_rref(matrix: &mut Array2<u8>) -> Vec<usize> {
+    let (height, width) = matrix.dim();
+    let mut pivots = vec![];
+
+    for col in 0..(width - 1) {
+        let row = pivots.len();
+        let Some(found) = (row..height).find(|&r| matrix[(r, col)] == 1) else {
+            continue;
+        };
+
+        swap_rows(matrix, row, found);
+
+        for other in 0..height {
+            if other == row || matrix[(other, col)] == 0 {
+                continue;
+            }
+
+            let (mut target, pivot_row) = matrix.multi_slice_mut((s![other, ..], s![row, ..]));
+            target ^= &pivot_row;
+        }
+
+        pivots.push(col);
+    }
+
+    pivots
+}
+
+/// Fraction free version of the reduced row echelon form: instead of dividing by the pivot (and
+/// needing rationals) every other row is scaled by it, pivots end up positive but not always 1
+fn integer_rref(matrix: &mut Array2<i64>) -> Vec<usize> {
+    let (height, width) = matrix.dim();
+    let mut pivots = vec![];
+
+    for col in 0..(width - 1) {
+        let row = pivots.len();
+        let Some(found) = (row..height).find(|&r| matrix[(r, col)] != 0) else {
+            continue;
+        };
+
+        swap_rows(matrix, row, found);
+        if matrix[(row, col)] < 0 {
+            matrix.row_mut(row).mapv_inplace(|v| -v);
+        }
+
+        normalize_row(matrix.row_mut(row));
+
+        let pivot = matrix[(row, col)];
+        for other in 0..height {
+            let factor = matrix[(other, col)];
+            if other == row || factor == 0 {
+                continue;
+            }
+
+            let (mut target, pivot_row) = matrix.multi_slice_mut((s![other, ..], s![row, ..]));
+            Zip::from(&mut target)
+                .and(&pivot_row)
+                .for_each(|t, &p| *t = *t * pivot - factor * p);
+            normalize_row(target);
+        }
+
+        pivots.push(col);
+    }
+
+    pivots
+}
+
+/// The joltage equations once reduced, only the free presses are left to choose
+struct JoltageSystem {
+    matrix: Array2<i64>,
+    pivots: Vec<usize>,
+    free: Vec<usize>,
+    /// Counters increased by each button
+    wiring: Vec<Vec<usize>>,
+    /// Change of the total presses for every press of each free button, once the pivot buttons
+    /// make up for it
+    costs: Vec<f64>,
+}
+
+impl JoltageSystem {
+    fn new(matrix: Array2<i64>, pivots: Vec<usize>, wiring: Vec<Vec<usize>>) -> Self {
+        let cost = |f: usize| {
+            pivots.iter().enumerate().fold(1.0, |cost, (row, &col)| {
+                cost - matrix[(row, f)] as f64 / matrix[(row, col)] as f64
+            })
+        };
+
+        // Buttons changing the total the most are chosen first so the bound tightens quickly, the
+        // ones that do not change it come last and are cut as soon as one of their choices works
+        let (free, costs) = (0..wiring.len())
+            .filter(|col| !pivots.contains(col))
+            .map(|f| (f, cost(f)))
+            .sorted_by(|a, b| b.1.abs().total_cmp(&a.1.abs()))
+            .unzip();
+
+        Self {
+            matrix,
+            pivots,
+            free,
+            wiring,
+            costs,
+        }
+    }
+
+    /// Right hand side of `row` once the first free buttons are pressed `presses` times, the
+    /// pivot button of the row is pressed this divided by the pivot
+    fn pivot_value(&self, row: usize, presses: &[u64]) -> i64 {
+        let rhs = self.matrix.ncols() - 1;
+        self.matrix[(row, rhs)]
+            - self
+                .free
+                .iter()
+                .zip(presses)
+                .map(|(&f, &p)| self.matrix[(row, f)] * p as i64)
+                .sum::<i64>()
+    }
+
+    /// Total presses if the free buttons are pressed `presses` times, `None` if the pivot buttons
+    /// would need a negative or fractional amount of presses
+    fn evaluate(&self, presses: &[u64]) -> Option<u64> {
+        let mut total: u64 = presses.iter().sum();
+
+        for (row, &col) in self.pivots.iter().enumerate() {
+            let value = self.pivot_value(row, presses);
+            let pivot = self.matrix[(row, col)];
+            if value < 0 || value % pivot != 0 {
+                return None;
+            }
+
+            total += (value / pivot) as u64;
+        }
+
+        Some(total)
+    }
+
+    /// Most presses of `button` that do not go over the joltage still needed by its counters
+    fn most_presses(&self, button: usize, remaining: &[u64]) -> u64 {
+        self.wiring[button]
+            .iter()
+            .map(|&i| remaining[i])
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Fewest and most presses of the free buttons not chosen yet, narrowed until every pivot
+    /// button can still be pressed between 0 and its own most presses, `None` once one of them
+    /// cannot
+    fn free_ranges(&self, presses: &[u64], remaining: &[u64]) -> Option<Vec<(i64, i64)>> {
+        let left = &self.free[presses.len()..];
+        let mut ranges = left
+            .iter()
+            .map(|&f| (0, self.most_presses(f, remaining) as i64))
+            .collect_vec();
+        let rows = self
+            .pivots
+            .iter()
+            .enumerate()
+            .map(|(row, &col)| {
+                let most = self.matrix[(row, col)] * self.most_presses(col, remaining) as i64;
+                (row, self.pivot_value(row, presses), most)
+            })
+            .collect_vec();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(row, value, most) in &rows {
+                let coefficients = left.iter().map(|&f| self.matrix[(row, f)]);
+                let (mut low, mut high) = (value, value);
+                for (c, &(fewest, most)) in coefficients.clone().zip(&ranges) {
+                    let (a, b) = (c * fewest, c * most);
+                    low -= a.max(b);
+                    high -= a.min(b);
+                }
+
+                if high < 0 || low > most {
+                    return None;
+                }
+
+                // Outside of this range the row cannot be brought back between 0 and its most
+                // whatever the other buttons do
+                for (c, range) in coefficients.zip(&mut ranges) {
+                    let narrowed = match c.signum() {
+                        1 => (
+                            -(most - low - c * range.1).div_euclid(c),
+                            (high + c * range.0).div_euclid(c),
+                        ),
+                        -1 => (
+                            -(high + c * range.1).div_euclid(-c),
+                            (most - low - c * range.0).div_euclid(-c),
+                        ),
+                        _ => continue,
+                    };
+
+                    let narrowed = (narrowed.0.max(range.0), narrowed.1.min(range.1));
+                    if narrowed.0 > narrowed.1 {
+                        return None;
+                    }
+
+                    if narrowed != *range {
+                        *range = narrowed;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        Some(ranges)
+    }
+
+    /// Lower bound of the total presses once the free buttons not chosen yet are pressed within
+    /// `ranges`
+    fn fewest_presses(&self, presses: &[u64], ranges: &[(i64, i64)]) -> f64 {
+        let chosen: u64 = presses.iter().sum();
+        let pivots: f64 = self
+            .pivots
+            .iter()
+            .enumerate()
+            .map(|(row, &col)| {
+                self.pivot_value(row, presses) as f64 / self.matrix[(row, col)] as f64
+            })
+            .sum();
+        let left: f64 = self.costs[presses.len()..]
+            .iter()
+            .zip(ranges)
+            .map(|(&cost, &(fewest, most))| (cost * fewest as f64).min(cost * most as f64))
+            .sum();
+
+        chosen as f64 + pivots + left
+    }
+
+    /// Branch and bound over the free presses, `remaining` is the joltage each counter still
+    /// needs once the free buttons chosen so far are pressed
+    fn search(
+        &self,
+        presses: &mut Vec<u64>,
+        remaining: &mut [u64],
+        partial: u64,
+        best: &mut Option<u64>,
+    ) {
+        let Some(ranges) = self.free_ranges(presses, remaining) else {
+            return;
+        };
+
+        // Totals are integers, a bound within half a press of the best is no better
+        if best.is_some_and(|b| self.fewest_presses(presses, &ranges) > b as f64 - 0.5) {
+            return;
+        }
+
+        if presses.len() == self.free.len() {
+            if let Some(total) = self.evaluate(presses) {
+                *best = Some(best.map_or(total, |b| b.min(total)));
+            }
+
+            return;
+        }
+
+        let wiring = &self.wiring[self.free[presses.len()]];
+        let (fewest, most) = ranges[0];
+        for count in fewest as u64..=most as u64 {
+            if best.is_some_and(|b| partial + count >= b) {
+                break;
+            }
+
+            wiring.iter().for_each(|&i| remaining[i] -= count);
+            presses.push(count);
+            self.search(presses, remaining, partial + count, best);
+            presses.pop();
+            wiring.iter().for_each(|&i| remaining[i] += count);
+        }
+    }
+}
+
+impl Machine {
+    /// Minimum amount of button presses needed to get the lights to match the diagram
+    ///
+    /// Pressing a button twice is the same as not pressing it, so this is `A x = b` over GF(2);
+    /// every solution is the particular one xor'ed with a combination of the null space basis
+    fn min_light_presses(&self) -> Option<u32> {
+        let buttons = self.buttons.len();
+
+        let mut matrix = Array2::zeros((self.lights.len(), buttons + 1));
+        for (j, wiring) in self.buttons.iter().enumerate() {
+            for &i in wiring {
+                matrix[(i, j)] ^= 1;
+            }
+        }
+
+        for (i, &light) in self.lights.iter().enumerate() {
+            matrix[(i, buttons)] = light as u8;
+        }
+
+        let pivots = gf2_rref(&mut matrix);
+        if (pivots.len()..self.lights.len()).any(|row| matrix[(row, buttons)] == 1) {
+            return None;
+        }
+
+        let particular = pivots
+            .iter()
+            .enumerate()
+            .filter(|&(row, _)| matrix[(row, buttons)] == 1)
+            .fold(0u64, |acc, (_, &col)| acc | 1 << col);
+
+        // A button that toggles no light (wired to each of its lights an even amount of times)
+        // would only add a press, leaving it out also keeps the null space under 64 vectors
+        let null_space = (0..buttons)
+            .filter(|col| !pivots.contains(col))
+            .filter(|&col| matrix.column(col).iter().any(|&v| v == 1))
+            .map(|free| {
+                pivots
+                    .iter()
+                    .enumerate()
+                    .filter(|&(row, _)| matrix[(row, free)] == 1)
+                    .fold(1u64 << free, |acc, (_, &col)| acc | 1 << col)
+            })
+            .collect_vec();
+
+        (0..1u64 << null_space.len())
+            .map(|combination| {
+                null_space
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| combination & 1 << i != 0)
+                    .fold(particular, |acc, (_, &v)| acc ^ v)
+                    .count_ones()
+            })
+            .min()
+    }
+
+    /// Minimum amount of button presses needed to get every counter to its exact joltage
+    ///
+    /// This is an integer linear program, the equations are reduced with `integer_rref` then the
+    /// free buttons are enumerated with branch and bound: each is bounded by the joltage still
+    /// needed by its counters and by the range the pivot buttons can take
+    fn min_joltage_presses(&self) -> Option<u64> {
+        let buttons = self.buttons.len();
+
+        let mut matrix = Array2::zeros((self.joltage.len(), buttons + 1));
+        for (j, wiring) in self.buttons.iter().enumerate() {
+            for &i in wiring {
+                matrix[(i, j)] += 1;
+            }
+        }
+
+        for (i, &joltage) in self.joltage.iter().enumerate() {
+            matrix[(i, buttons)] = joltage as i64;
+        }
+
+        let pivots = integer_rref(&mut matrix);
+        if (pivots.len()..self.joltage.len()).any(|row| matrix[(row, buttons)] != 0) {
+            return None;
+        }
+
+        let system = JoltageSystem::new(matrix, pivots, self.buttons.clone());
+
+        let mut best = None;
+        system.search(&mut vec![], &mut self.joltage.clone(), 0, &mut best);
+        best
+    }
+}
+
+#[aoc(day10, part1)]
+fn part1(input: &[Machine]) -> u32 {
+    input
+        .iter()
+        .map(|machine| {
+            machine
+                .min_light_presses()
+                .unwrap_or_else(|| panic!("Lights of {:?} cannot be configured", machine))
+        })
+        .sum()
+}
+
+#[aoc(day10, part2)]
+fn part2(input: &[Machine]) -> u64 {
+    input
+        .iter()
+        .map(|machine| {
+            machine
+                .min_joltage_presses()
+                .unwrap_or_else(|| panic!("Joltages of {:?} cannot be configured", machine))
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn brute_force_joltage(machine: &Machine) -> Option<u64> {
+        machine
+            .buttons
+            .iter()
+            .map(|wiring| {
+                let bound = wiring.iter().map(|&i| machine.joltage[i]).min();
+                0..=bound.unwrap_or_default()
+            })
+            .multi_cartesian_product()
+            .filter(|presses| {
+                let mut counters = vec![0; machine.joltage.len()];
+                for (wiring, &count) in machine.buttons.iter().zip(presses) {
+                    wiring.iter().for_each(|&i| counters[i] += count);
+                }
+
+                counters == machine.joltage
+            })
+            .map(|presses| presses.into_iter().sum())
+            .min()
+    }
+
+    fn random_machine(rng: &mut StdRng) -> Machine {
+        let counters = rng.random_range(1..=4);
+        let buttons = (0..rng.random_range(1..=5))
+            .map(|_| {
+                let wiring = (0..counters).filter(|_| rng.random_bool(0.5)).collect_vec();
+                if wiring.is_empty() {
+                    vec![rng.random_range(0..counters)]
+                } else {
+                    wiring
+                }
+            })
+            .collect_vec();
+
+        let joltage = if rng.random_bool(0.8) {
+            let mut joltage = vec![0; counters];
+            for wiring in &buttons {
+                let count = rng.random_range(0..=3);
+                wiring.iter().for_each(|&i| joltage[i] += count);
+            }
+
+            joltage
+        } else {
+            (0..counters).map(|_| rng.random_range(0..=6)).collect()
+        };
+
+        Machine {
+            lights: vec![false; counters],
+            buttons,
+            joltage,
+        }
+    }
+
+    const EXAMPLE: &str = "
+        [.##.] (3) (1,3) (2) (2,3) (0,2) (0,1) {3,5,4,7}
+        [...#.] (0,2,3,4) (2,3) (0,4) (0,1,2) (1,2,3,4) {7,5,12,7,2}
+        [.###.#] (0,1,2,3,4) (0,3,4) (0,1,2,4,5) (1,2) {10,11,11,5,10,5}
+";
+
+    #[test]
+    fn part1_example() {
+        assert_eq!(part1(&parse(EXAMPLE).expect("Could not parse")), 7);
+    }
+
+    #[test]
+    fn part1_example_machines() {
+        let machines = parse(EXAMPLE).expect("Could not parse");
+        let presses = machines
+            .iter()
+            .map(Machine::min_light_presses)
+            .collect_vec();
+
+        assert_eq!(presses, [Some(2), Some(3), Some(2)]);
+    }
+
+    #[test]
+    fn part1_unreachable() {
+        let machines = parse("[##] (0) (0) {1,1}").expect("Could not parse");
+        assert_eq!(machines[0].min_light_presses(), None);
+    }
+
+    #[test]
+    fn part1_most_buttons() {
+        let line = format!("[.] {}{{0}}", "(0,0) ".repeat(64));
+        let machines = parse(&line).expect("Could not parse");
+        assert_eq!(machines[0].min_light_presses(), Some(0));
+    }
+
+    #[test]
+    fn part2_example() {
+        assert_eq!(part2(&parse(EXAMPLE).expect("Could not parse")), 33);
+    }
+
+    #[test]
+    fn part2_example_machines() {
+        let machines = parse(EXAMPLE).expect("Could not parse");
+        let presses = machines
+            .iter()
+            .map(Machine::min_joltage_presses)
+            .collect_vec();
+
+        assert_eq!(presses, [Some(10), Some(12), Some(11)]);
+    }
+
+    #[test]
+    fn part2_unreachable() {
+        let machines = parse("[..] (0,1) {1,2}").expect("Could not parse");
+        assert_eq!(machines[0].min_joltage_presses(), None);
+    }
+
+    #[test]
+    fn part2_random_machines() {
+        let mut rng = StdRng::seed_from_u64(2025);
+
+        for _ in 0..200 {
+            let machine = random_machine(&mut rng);
+            assert_eq!(
+                machine.min_joltage_presses(),
+                brute_force_joltage(&machine),
+                "{:?}",
+                machine
+            );
+        }
+    }
+
+    #[test]
+    fn part2_large_machines() {
+        let machines = parse(
+            "
+            [..........] (3) (5) (1,6) (3,4) (3) (6) (2,3) (7) (7) (8) (8) (3,5,8) (5,6) {0,34,26,160,38,101,99,70,95,0}
+            [..........] (1) (1) (4,6,8) (1,5,8) (4) (4) (2,7) (7,9) (6) (6) (4,6,7) (9) (2,6) {0,90,62,0,125,32,160,114,60,77}
+        ",
+        )
+        .expect("Could not parse");
+        let presses = machines
+            .iter()
+            .map(Machine::min_joltage_presses)
+            .collect_vec();
+
+        assert_eq!(presses, [Some(329), Some(354)]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse("[.#] (0) (2) {1,1}").expect_err("Parsed a missing light");
+        assert_eq!(
+            err.to_string(),
+            "day 10, line 1, column 11: invalid \"2\", there are only 2 lights"
+        );
+
+        let err = parse("[.#] (0) {1,1").expect_err("Parsed unclosed joltages");
+        assert_eq!(
+            err.to_string(),
+            "day 10, line 1, column 10: unexpected \"{1,1\", expected '{'...'}'"
+        );
+
+        let line = format!("[.] {}(0) {{1}}", "(0) ".repeat(64));
+        let err = parse(&line).expect_err("Parsed 65 buttons");
+        assert_eq!(
+            err.to_string(),
+            "day 10, line 1, column 261: invalid \"(0)\", machines have at most 64 buttons"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::error::Error;
+
+use fnv::{FnvHashMap, FnvHashSet};
+use itertools::Itertools;
+
+use crate::input::{self, ParseError};
+
+/// Devices and the devices their outputs are connected to
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    names: Vec<String>,
+    ids: FnvHashMap<String, usize>,
+    outputs: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = self.names.len();
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), id);
+        self.outputs.push(vec![]);
+        id
+    }
+
+    fn id(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("No device named {:?}", name).into())
+    }
+}
+
+#[aoc_generator(day11)]
+pub fn parse(input: &str) -> Result<Graph, ParseError> {
+    let mut graph = Graph::default();
+    // Devices listed so far, some of them without any output
+    let mut listed = FnvHashSet::default();
+
+    for line in input::lines(11, input) {
+        let Some((device, outputs)) = line.text.split_once(':') else {
+            return Err(line.unexpected(line.text, "'<device>: <outputs>'"));
+        };
+
+        let device_id = graph.intern(device.trim());
+        if !listed.insert(device_id) {
+            return Err(line.invalid(device, "device is listed twice"));
+        }
+
+        let outputs = outputs
+            .split_whitespace()
+            .map(|output| graph.intern(output))
+            .collect_vec();
+        graph.outputs[device_id] = outputs;
+    }
+
+    Ok(graph)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    InProgress,
+    Done(u128),
+}
+
+/// Memoization is keyed on the node and the mask of the required nodes already visited on the way
+fn count_paths_logic(
+    graph: &Graph,
+    node: usize,
+    mut visited: u64,
+    end: usize,
+    required: &[usize],
+    visits: &mut FnvHashMap<(usize, u64), Visit>,
+) -> Result<u128, Box<dyn Error>> {
+    if let Some(idx) = required.iter().position(|&r| r == node) {
+        visited |= 1 << idx;
+    }
+
+    if node == end {
+        let all_visited = visited.count_ones() as usize == required.len();
+        return Ok(all_visited as u128);
+    }
+
+    match visits.get(&(node, visited)) {
+        Some(Visit::Done(cached)) => return Ok(*cached),
+        Some(Visit::InProgress) => {
+            return Err(format!("Cycle going through device {:?}", graph.names[node]).into());
+        }
+        None => (),
+    }
+
+    visits.insert((node, visited), Visit::InProgress);
+
+    let mut res = 0;
+    for &output in &graph.outputs[node] {
+        res += count_paths_logic(graph, output, visited, end, required, visits)?;
+    }
+
+    visits.insert((node, visited), Visit::Done(res));
+    Ok(res)
+}
+
+/// Counts the paths going from `start` to `end` that visit every device in `required` (in any
+/// order), errors out if a cycle is reachable from `start` before getting to `end` (there would
+/// be infinitely many paths)
+pub fn count_paths_through(
+    graph: &Graph,
+    start: &str,
+    end: &str,
+    required: &[&str],
+) -> Result<u128, Box<dyn Error>> {
+    let required: Vec<usize> = required.iter().map(|name| graph.id(name)).try_collect()?;
+    let required = required.into_iter().unique().collect_vec();
+
+    if required.len() > u64::BITS as usize {
+        return Err(format!("Cannot require more than {} devices", u64::BITS).into());
+    }
+
+    count_paths_logic(
+        graph,
+        graph.id(start)?,
+        0,
+        graph.id(end)?,
+        &required,
+        &mut FnvHashMap::default(),
+    )
+}
+
+pub fn count_paths(graph: &Graph, start: &str, end: &str) -> Result<u128, Box<dyn Error>> {
+    count_paths_through(graph, start, end, &[])
+}
+
+#[aoc(day11, part1)]
+fn part1(input: &Graph) -> Result<u128, Box<dyn Error>> {
+    count_paths(input, "you", "out")
+}
+
+#[aoc(day11, part2)]
+fn part2(input: &Graph) -> Result<u128, Box<dyn Error>> {
+    count_paths_through(input, "svr", "out", &["dac", "fft"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "
+        aaa: you hhh
+        you: bbb ccc
+        bbb: ddd eee
+        ccc: ddd eee fff
+        ddd: ggg
+        eee: out
+        fff: out
+        ggg: out
+        hhh: ccc fff iii
+        iii: out
+";
+
+    #[test]
+    fn part1_example() {
+        let graph = parse(EXAMPLE).expect("Could not parse");
+        assert_eq!(part1(&graph).expect("Could not count paths"), 5);
+    }
+
+    const EXAMPLE2: &str = "
+        svr: aaa bbb
+        aaa: fft
+        fft: ccc
+        bbb: tty
+        tty: ccc
+        ccc: ddd eee
+        ddd: hub
+        hub: fff
+        eee: dac
+        dac: fff
+        fff: ggg hhh
+        ggg: out
+        hhh: out
+";
+
+    #[test]
+    fn part1_cycle() {
+        let graph = parse("you: aaa\naaa: bbb out\nbbb: aaa").expect("Could not parse");
+        let err = count_paths(&graph, "you", "out").expect_err("Cycle was not detected");
+        assert!(err.to_string().contains("Cycle"), "{}", err);
+    }
+
+    #[test]
+    fn parse_errors() {
+        for input in [
+            "aaa: bbb\naaa: ccc",
+            "aaa:\naaa: bbb",
+            "aaa: bbb\nbbb:\nbbb:",
+        ] {
+            let err = parse(input).expect_err("Parsed a device listed twice");
+            assert!(
+                err.to_string().ends_with("device is listed twice"),
+                "{}",
+                err
+            );
+        }
+
+        let err = parse("aaa: bbb\naaa:").expect_err("Parsed a device listed twice");
+        assert_eq!(
+            err.to_string(),
+            "day 11, line 2, column 1: invalid \"aaa\", device is listed twice"
+        );
+    }
+
+    #[test]
+    fn part1_unknown_device() {
+        let graph = parse(EXAMPLE).expect("Could not parse");
+        assert!(count_paths(&graph, "svr", "out").is_err());
+    }
+
+    #[test]
+    fn part2_example() {
+        let graph = parse(EXAMPLE2).expect("Could not parse");
+        assert_eq!(part2(&graph).expect("Could not count paths"), 2);
+    }
+
+    #[test]
+    fn part2_required_subsets() {
+        let graph = parse(EXAMPLE2).expect("Could not parse");
+        let count = |required: &[&str]| {
+            count_paths_through(&graph, "svr", "out", required).expect("Could not count paths")
+        };
+
+        assert_eq!(count(&[]), 8);
+        assert_eq!(count(&["fft"]), 4);
+        assert_eq!(count(&["dac"]), 4);
+        assert_eq!(count(&["dac", "dac"]), 4);
+        assert_eq!(count(&["hub", "dac"]), 0);
+    }
+
+    #[test]
+    fn part2_large_counts() {
+        // 100 diamonds in a row, 2^100 paths does not fit a u64
+        let input = (0..100)
+            .map(|i| {
+                format!(
+                    "n{i}: a{i} b{i}\na{i}: n{next}\nb{i}: n{next}",
+                    next = i + 1
+                )
+            })
+            .join("\n");
+        let graph = parse(&input).expect("Could not parse");
+
+        assert_eq!(
+            count_paths_through(&graph, "n0", "n100", &["a50", "b99"]).expect("Could not count"),
+            1 << 98
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use fnv::FnvHashSet;
+use itertools::Itertools;
+
+use crate::input::{self, InputLine, ParseError};
+
+type Offset = (isize, isize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Shape {
+    cells: Vec<(usize, usize)>,
+    /// Every distinct rotation and reflection, as offsets from its first cell in reading order
+    orientations: Vec<Vec<Offset>>,
+}
+
+impl Shape {
+    fn new(cells: Vec<(usize, usize)>) -> Self {
+        let orientations = (0..8)
+            .map(|transform| {
+                let transformed = cells
+                    .iter()
+                    .map(|&(r, c)| {
+                        let (r, c) = (r as isize, c as isize);
+                        let (r, c) = if transform & 1 != 0 { (c, r) } else { (r, c) };
+                        let r = if transform & 2 != 0 { -r } else { r };
+                        if transform & 4 != 0 { (r, -c) } else { (r, c) }
+                    })
+                    .sorted()
+                    .collect_vec();
+
+                let anchor = transformed[0];
+                transformed
+                    .into_iter()
+                    .map(|(r, c)| (r - anchor.0, c - anchor.1))
+                    .collect_vec()
+            })
+            .unique()
+            .collect();
+
+        Self {
+            cells,
+            orientations,
+        }
+    }
+
+    fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Side of the smallest square containing the shape whatever its orientation
+    fn bounding_side(&self) -> usize {
+        let height = self.cells.iter().map(|c| c.0).max().unwrap_or_default() + 1;
+        let width = self.cells.iter().map(|c| c.1).max().unwrap_or_default() + 1;
+        height.max(width)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Region {
+    width: usize,
+    height: usize,
+    counts: Vec<usize>,
+}
+
+fn parse_shape<'a>(
+    header: InputLine,
+    lines: &mut impl Iterator<Item = InputLine<'a>>,
+) -> Result<Shape, ParseError> {
+    let mut cells = vec![];
+
+    for (row, line) in lines.take_while(|line| !line.text.is_empty()).enumerate() {
+        for (col, (i, c)) in line.text.char_indices().enumerate() {
+            match c {
+                '#' => cells.push((row, col)),
+                '.' => (),
+                _ => return Err(line.unexpected(&line.text[i..(i + c.len_utf8())], "'.' or '#'")),
+            }
+        }
+    }
+
+    if cells.is_empty() {
+        return Err(header.invalid(header.text, "shape is empty"));
+    }
+
+    Ok(Shape::new(cells))
+}
+
+fn parse_region(line: InputLine, shapes: usize) -> Result<Region, ParseError> {
+    let Some((size, counts)) = line.text.split_once(':') else {
+        return Err(line.unexpected(line.text, "a shape index or 'WxH: counts'"));
+    };
+
+    let Some((width, height)) = size.split_once('x') else {
+        return Err(line.unexpected(size, "'WxH'"));
+    };
+
+    let counts: Vec<usize> = counts
+        .split_whitespace()
+        .map(|count| line.parse(count))
+        .try_collect()?;
+    if counts.len() != shapes {
+        return Err(line.invalid(
+            line.text,
+            format!("{} counts for {} shapes", counts.len(), shapes),
+        ));
+    }
+
+    Ok(Region {
+        width: line.parse(width.trim())?,
+        height: line.parse(height.trim())?,
+        counts,
+    })
+}
+
+#[aoc_generator(day12)]
+fn parse(input: &str) -> Result<(Vec<Shape>, Vec<Region>), ParseError> {
+    let mut lines = input::all_lines(12, input);
+    let mut shapes = vec![];
+    let mut regions = vec![];
+
+    while let Some(line) = lines.next() {
+        if line.text.is_empty() {
+            continue;
+        }
+
+        let Some(header) = line.text.strip_suffix(':') else {
+            regions.push(parse_region(line, shapes.len())?);
+            continue;
+        };
+
+        let index: usize = line.parse(header)?;
+        if index != shapes.len() || !regions.is_empty() {
+            return Err(line.invalid(header, "shape is out of order"));
+        }
+
+        shapes.push(parse_shape(line, &mut lines)?);
+    }
+
+    Ok((shapes, regions))
+}
+
+/// Cells from the first free one to the farthest a shape anchored there can reach, every cell
+/// before it is decided and every cell after it is still free
+type PackingState = (usize, Vec<u64>, Vec<usize>);
+
+struct Packing<'a> {
+    shapes: &'a [Shape],
+    width: usize,
+    height: usize,
+    reach: usize,
+    grid: Vec<bool>,
+    counts: Vec<usize>,
+    dead_ends: FnvHashSet<PackingState>,
+}
+
+impl Packing<'_> {
+    fn cell_index(&self, cell: usize, (dr, dc): Offset) -> Option<usize> {
+        let r = (cell / self.width) as isize + dr;
+        let c = (cell % self.width) as isize + dc;
+        if r < 0 || c < 0 || r as usize >= self.height || c as usize >= self.width {
+            return None;
+        }
+
+        Some(r as usize * self.width + c as usize)
+    }
+
+    fn cells(&self, cell: usize, orientation: &[Offset]) -> Option<Vec<usize>> {
+        orientation
+            .iter()
+            .map(|&offset| {
+                let idx = self.cell_index(cell, offset)?;
+                (!self.grid[idx]).then_some(idx)
+            })
+            .collect()
+    }
+
+    fn fits(&self, cell: usize, orientation: &[Offset]) -> bool {
+        orientation.iter().all(|&offset| {
+            self.cell_index(cell, offset)
+                .is_some_and(|idx| !self.grid[idx])
+        })
+    }
+
+    /// Whether any remaining shape could still be placed over the free cell `idx`
+    fn coverable(&self, idx: usize) -> bool {
+        self.shapes
+            .iter()
+            .zip(&self.counts)
+            .filter(|&(_, &count)| count > 0)
+            .flat_map(|(shape, _)| &shape.orientations)
+            .any(|orientation| {
+                orientation.iter().any(|&(dr, dc)| {
+                    self.cell_index(idx, (-dr, -dc))
+                        .is_some_and(|anchor| self.fits(anchor, orientation))
+                })
+            })
+    }
+
+    fn state(&self, cell: usize) -> PackingState {
+        let window = self.grid[cell..(cell + self.reach).min(self.grid.len())]
+            .chunks(u64::BITS as usize)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, &filled| acc << 1 | filled as u64)
+            })
+            .collect();
+
+        (cell, window, self.counts.clone())
+    }
+
+    /// The first free cell is either covered by a shape whose first cell lands on it, or left
+    /// empty which uses up some of the `slack` (free area the shapes will not cover)
+    ///
+    /// The slack is fully determined by the state so states that failed once are not retried
+    fn search(&mut self, from: usize, slack: usize) -> bool {
+        if self.counts.iter().all(|&c| c == 0) {
+            return true;
+        }
+
+        let Some(cell) = (from..self.grid.len()).find(|&idx| !self.grid[idx]) else {
+            return false;
+        };
+
+        let state = self.state(cell);
+        if self.dead_ends.contains(&state) {
+            return false;
+        }
+
+        let window = cell..(cell + self.reach).min(self.grid.len());
+        let wasted = window
+            .filter(|&idx| !self.grid[idx] && !self.coverable(idx))
+            .count();
+
+        let found = wasted <= slack && self.search_from(cell, slack);
+        if !found {
+            self.dead_ends.insert(state);
+        }
+
+        found
+    }
+
+    fn search_from(&mut self, cell: usize, slack: usize) -> bool {
+        for shape in 0..self.shapes.len() {
+            if self.counts[shape] == 0 {
+                continue;
+            }
+
+            for orientation in &self.shapes[shape].orientations {
+                let Some(cells) = self.cells(cell, orientation) else {
+                    continue;
+                };
+
+                cells.iter().for_each(|&idx| self.grid[idx] = true);
+                self.counts[shape] -= 1;
+
+                let found = self.search(cell + 1, slack);
+
+                self.counts[shape] += 1;
+                cells.iter().for_each(|&idx| self.grid[idx] = false);
+
+                if found {
+                    return true;
+                }
+            }
+        }
+
+        if slack == 0 {
+            return false;
+        }
+
+        self.grid[cell] = true;
+        let found = self.search(cell + 1, slack - 1);
+        self.grid[cell] = false;
+
+        found
+    }
+}
+
+/// Decides whether every present of `region` fits, the area and bounding box checks settle most
+/// regions, the rest goes through backtracking
+fn region_fits(shapes: &[Shape], region: &Region) -> bool {
+    let area = region.width * region.height;
+    let needed: usize = shapes
+        .iter()
+        .zip(region.counts.iter().copied())
+        .map(|(shape, count)| shape.area() * count)
+        .sum();
+
+    if needed > area {
+        return false;
+    }
+
+    let side = shapes.iter().map(Shape::bounding_side).max().unwrap_or(1);
+    let presents: usize = region.counts.iter().sum();
+    if (region.width / side) * (region.height / side) >= presents {
+        return true;
+    }
+
+    // Shapes can be rotated so the region can be too, scanning along the short side keeps the
+    // window of undecided cells small
+    let width = region.width.min(region.height);
+
+    Packing {
+        shapes,
+        width,
+        height: area / width,
+        reach: side * width,
+        grid: vec![false; area],
+        counts: region.counts.clone(),
+        dead_ends: FnvHashSet::default(),
+    }
+    .search(0, area - needed)
+}
+
+#[aoc(day12, part1)]
+fn part1(input: &(Vec<Shape>, Vec<Region>)) -> usize {
+    input
+        .1
+        .iter()
+        .filter(|region| region_fits(&input.0, region))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "
+        0:
+        ###
+        ##.
+        ##.
+
+        1:
+        ###
+        ##.
+        .##
+
+        2:
+        .##
+        ###
+        ##.
+
+        3:
+        ##.
+        ###
+        ##.
+
+        4:
+        ###
+        #..
+        ###
+
+        5:
+        ###
+        .#.
+        ###
+
+        4x4: 0 0 0 0 2 0
+        12x5: 1 0 1 0 2 2
+        12x5: 1 0 1 0 3 2
+";
+
+    #[test]
+    fn part1_example() {
+        assert_eq!(part1(&parse(EXAMPLE).expect("Could not parse")), 2);
+    }
+
+    #[test]
+    fn part1_example_regions() {
+        let (shapes, regions) = parse(EXAMPLE).expect("Could not parse");
+        let fits = regions
+            .iter()
+            .map(|region| region_fits(&shapes, region))
+            .collect_vec();
+
+        assert_eq!(fits, [true, true, false]);
+    }
+
+    #[test]
+    fn orientations() {
+        let counts = ["####", "##\n##", ".##\n##.", "###\n#..", "###\n.#.\n###"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, shape)| {
+                let mut lines = input::all_lines(12, shape);
+                let header = InputLine::new(12, 0, "");
+                parse_shape(header, &mut lines)
+                    .unwrap_or_else(|err| panic!("Shape {}: {}", i, err))
+                    .orientations
+                    .len()
+            })
+            .collect_vec();
+
+        assert_eq!(counts, [2, 1, 4, 8, 2]);
+    }
+
+    #[test]
+    fn backtracking() {
+        let (shapes, regions) = parse(
+            "
+            0:
+            ###
+            #..
+
+            1:
+            .##
+            ##.
+
+            4x2: 2 0
+            3x3: 0 2
+            4x4: 0 4",
+        )
+        .expect("Could not parse");
+        let fits = regions
+            .iter()
+            .map(|region| region_fits(&shapes, region))
+            .collect_vec();
+
+        assert_eq!(fits, [true, false, false]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse("0:\n#é\n\n2x2: 1").expect_err("Parsed an accent");
+        assert_eq!(
+            err.to_string(),
+            "day 12, line 2, column 2: unexpected \"é\", expected '.' or '#'"
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+
+use itertools::Itertools;
+use ndarray::Array2;
+
+use crate::input::{self, InputLine, ParseError};
+
+pub type Position = (usize, usize);
+
+/// What to strip from every line before it becomes a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Trim {
+    None,
+    Start,
+    End,
+    #[default]
+    Both,
+}
+
+impl Trim {
+    fn apply(self, line: &str) -> &str {
+        match self {
+            Trim::None => line,
+            Trim::Start => line.trim_start(),
+            Trim::End => line.trim_end(),
+            Trim::Both => line.trim(),
+        }
+    }
+}
+
+/// What to do with rows shorter than the longest one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Padding {
+    #[default]
+    Reject,
+    /// Extends the short rows with this byte (it still goes through the cell parser)
+    With(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridOptions {
+    pub trim: Trim,
+    pub padding: Padding,
+    pub skip_empty_lines: bool,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        Self {
+            trim: Trim::default(),
+            padding: Padding::default(),
+            skip_empty_lines: true,
+        }
+    }
+}
+
+/// Error for the cell at byte `col` of `trimmed` (a slice of `line`), pointing at the whole
+/// character the byte is part of, or past the end of the line for padding
+fn unexpected_cell(
+    line: InputLine,
+    trimmed: &str,
+    col: usize,
+    byte: u8,
+    err: impl fmt::Display,
+) -> ParseError {
+    if col < trimmed.len() {
+        let start = trimmed.floor_char_boundary(col);
+        let len = trimmed[start..].chars().next().map_or(1, char::len_utf8);
+        return line.unexpected(&trimmed[start..(start + len)], err.to_string());
+    }
+
+    let end = &trimmed[trimmed.len()..];
+    let mut at = line.location(end);
+    at.column += col - trimmed.len();
+    ParseError::Unexpected {
+        at,
+        text: (byte as char).to_string(),
+        expected: err.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    cells: Array2<T>,
+}
+
+impl<T> Grid<T> {
+    /// Builds the grid from already parsed rows (along with the input line they come from), which
+    /// all need to have the same length
+    pub fn from_rows<'a>(
+        day: u32,
+        rows: impl IntoIterator<Item = (InputLine<'a>, Vec<T>)>,
+    ) -> Result<Self, ParseError> {
+        let mut width = None;
+        let mut height = 0;
+        let mut cells = vec![];
+
+        for (line, row) in rows {
+            let expected = *width.get_or_insert(row.len());
+            if row.len() != expected {
+                return Err(line.invalid(
+                    line.text,
+                    format!(
+                        "row has length {} but previous rows have length {}",
+                        row.len(),
+                        expected
+                    ),
+                ));
+            }
+
+            height += 1;
+            cells.extend(row);
+        }
+
+        let width = width.ok_or_else(|| ParseError::missing(day, "the grid"))?;
+        Ok(Self {
+            cells: Array2::from_shape_vec((height, width), cells)
+                .expect("Shape was checked beforehand"),
+        })
+    }
+
+    /// Maps every byte of `input` through `parse_cell`, which also gets the position of the byte
+    /// in the grid, its errors describe what was expected instead of the byte
+    pub fn parse_with<E: fmt::Display>(
+        day: u32,
+        input: &str,
+        options: GridOptions,
+        parse_cell: impl FnMut(u8, Position) -> Result<T, E>,
+    ) -> Result<Self, ParseError> {
+        Self::parse_lines_with(day, input::all_lines(day, input), options, parse_cell)
+    }
+
+    /// Same as `parse_with` for input that was already split in lines
+    pub fn parse_lines_with<'a, E: fmt::Display>(
+        day: u32,
+        lines: impl IntoIterator<Item = InputLine<'a>>,
+        options: GridOptions,
+        mut parse_cell: impl FnMut(u8, Position) -> Result<T, E>,
+    ) -> Result<Self, ParseError> {
+        let mut lines = lines
+            .into_iter()
+            .map(|line| (line, options.trim.apply(line.raw).as_bytes().to_owned()))
+            .filter(|(_, bytes)| !options.skip_empty_lines || !bytes.is_empty())
+            .collect_vec();
+
+        if let Padding::With(pad) = options.padding {
+            let width = lines.iter().map(|l| l.1.len()).max().unwrap_or_default();
+            lines
+                .iter_mut()
+                .for_each(|(_, bytes)| bytes.resize(width, pad));
+        }
+
+        let rows: Vec<_> = lines
+            .into_iter()
+            .enumerate()
+            .map(|(row, (line, bytes))| {
+                let trimmed = options.trim.apply(line.raw);
+
+                let cells = bytes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(col, byte)| {
+                        parse_cell(byte, (row, col))
+                            .map_err(|err| unexpected_cell(line, trimmed, col, byte, err))
+                    })
+                    .try_collect()?;
+
+                Ok::<_, ParseError>((line, cells))
+            })
+            .try_collect()?;
+
+        Self::from_rows(day, rows)
+    }
+
+    /// Parses like `parse_with`, except the single `marker` byte is handed to `parse_cell` as
+    /// `replacement` and its position is returned alongside the grid
+    pub fn parse_with_marker<E: fmt::Display>(
+        day: u32,
+        input: &str,
+        options: GridOptions,
+        (marker, replacement): (u8, u8),
+        mut parse_cell: impl FnMut(u8, Position) -> Result<T, E>,
+    ) -> Result<(Self, Position), ParseError> {
+        let mut found: Option<Position> = None;
+
+        let grid = Self::parse_with(day, input, options, |byte, pos| {
+            if byte != marker {
+                return parse_cell(byte, pos).map_err(|err| err.to_string());
+            }
+
+            if let Some(first) = found {
+                return Err(format!(
+                    "a single {:?} marker (already at row {}, column {})",
+                    marker as char, first.0, first.1
+                ));
+            }
+
+            found = Some(pos);
+            parse_cell(replacement, pos).map_err(|err| err.to_string())
+        })?;
+
+        let start = found
+            .ok_or_else(|| ParseError::missing(day, format!("the {:?} marker", marker as char)))?;
+        Ok((grid, start))
+    }
+
+    pub fn into_array(self) -> Array2<T> {
+        self.cells
+    }
+}
+
+impl<T> std::ops::Deref for Grid<T> {
+    type Target = Array2<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digit(byte: u8, _: Position) -> Result<u8, &'static str> {
+        match byte {
+            b'0'..=b'9' => Ok(byte - b'0'),
+            b' ' => Ok(0),
+            _ => Err("a digit"),
+        }
+    }
+
+    #[test]
+    fn parse_trimmed() {
+        let grid = Grid::parse_with(4, "\n  12\n  34  \n", GridOptions::default(), digit)
+            .expect("Could not parse");
+
+        assert_eq!(grid.into_array(), ndarray::array![[1, 2], [3, 4]]);
+    }
+
+    #[test]
+    fn differing_length() {
+        let err = Grid::parse_with(4, "12\n345", GridOptions::default(), digit)
+            .expect_err("Parsed differing lengths");
+
+        assert_eq!(
+            err.to_string(),
+            "day 4, line 2, column 1: invalid \"345\", row has length 3 but previous rows have length 2"
+        );
+    }
+
+    #[test]
+    fn padding() {
+        let options = GridOptions {
+            trim: Trim::None,
+            padding: Padding::With(b' '),
+            ..Default::default()
+        };
+        let grid = Grid::parse_with(6, " 1\n234\n5", options, digit).expect("Could not parse");
+
+        assert_eq!(
+            grid.into_array(),
+            ndarray::array![[0, 1, 0], [2, 3, 4], [5, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn invalid_cell() {
+        let err = Grid::parse_with(4, "\n  12\n  3x", GridOptions::default(), digit)
+            .expect_err("Parsed a letter");
+
+        assert_eq!(
+            err.to_string(),
+            "day 4, line 3, column 4: unexpected \"x\", expected a digit"
+        );
+    }
+
+    #[test]
+    fn markers() {
+        let options = GridOptions::default();
+        let (grid, pos) = Grid::parse_with_marker(7, "12\nS4", options, (b'S', b'0'), digit)
+            .expect("Could not parse");
+        assert_eq!(grid.into_array(), ndarray::array![[1, 2], [0, 4]]);
+        assert_eq!(pos, (1, 0));
+
+        let missing = Grid::parse_with_marker(7, "12\n34", options, (b'S', b'0'), digit);
+        assert_eq!(missing, Err(ParseError::missing(7, "the 'S' marker")));
+
+        let duplicate = Grid::parse_with_marker(7, "S2\n3S", options, (b'S', b'0'), digit)
+            .expect_err("Parsed two markers");
+        assert_eq!(
+            duplicate.to_string(),
+            "day 7, line 2, column 2: unexpected \"S\", expected a single 'S' marker (already at row 0, column 0)"
+        );
+    }
+
+    #[test]
+    fn non_ascii_cell() {
+        let err = Grid::parse_with(4, "  1é2\n3456", GridOptions::default(), digit)
+            .expect_err("Parsed an accent");
+        assert_eq!(
+            err.to_string(),
+            "day 4, line 1, column 4: unexpected \"é\", expected a digit"
+        );
+
+        let options = GridOptions {
+            padding: Padding::With(b'x'),
+            ..Default::default()
+        };
+        let err = Grid::parse_with(4, " é1\n2345", options, |b, pos| match b {
+            b'x' => Err("no padding"),
+            _ => digit(b, pos).or(Ok(0)),
+        })
+        .expect_err("Parsed padding");
+        assert_eq!(
+            err.to_string(),
+            "day 4, line 1, column 4: unexpected \"x\", expected no padding"
+        );
+    }
+}
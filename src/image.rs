@@ -0,0 +1,280 @@
+use fnv::FnvHashMap;
+
+pub type Rgb = [u8; 3];
+
+/// An image whose pixels are indices into a palette of at most 256 colours
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl IndexedImage {
+    /// Builds the image from `pixel(row, column)`
+    pub fn from_fn(width: usize, height: usize, mut pixel: impl FnMut(usize, usize) -> u8) -> Self {
+        let pixels = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| pixel(row, col))
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Every pixel becomes a `factor` by `factor` square
+    pub fn scaled(&self, factor: usize) -> Self {
+        Self::from_fn(self.width * factor, self.height * factor, |row, col| {
+            self.pixels[(row / factor) * self.width + col / factor]
+        })
+    }
+}
+
+/// Binary PPM (P6) of `image`
+pub fn ppm(image: &IndexedImage, palette: &[Rgb]) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", image.width, image.height).into_bytes();
+    out.extend(image.pixels.iter().flat_map(|&p| palette[p as usize]));
+    out
+}
+
+/// Writes variable width codes starting from the least significant bit, as GIF wants them
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= u32::from(code) << self.len;
+        self.len += size;
+
+        while self.len >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+
+        self.bytes
+    }
+}
+
+const MAX_CODES: u16 = 1 << 12;
+
+/// LZW compression of `pixels` with the GIF flavour: codes grow from `min_code_size + 1` bits up
+/// to 12 bits, and the table is cleared once it is full
+fn lzw(min_code_size: u32, pixels: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut out = BitWriter::default();
+    let mut table: FnvHashMap<(u16, u8), u16> = FnvHashMap::default();
+    let mut next = end + 1;
+    let mut size = min_code_size + 1;
+    out.write(clear, size);
+
+    let Some((&first, rest)) = pixels.split_first() else {
+        out.write(end, size);
+        return out.finish();
+    };
+
+    let mut current = u16::from(first);
+    for &pixel in rest {
+        if let Some(&code) = table.get(&(current, pixel)) {
+            current = code;
+            continue;
+        }
+
+        out.write(current, size);
+        table.insert((current, pixel), next);
+        next += 1;
+
+        // The decoder adds its entries one code late, so it widens one code later too
+        if next > 1 << size && size < 12 {
+            size += 1;
+        }
+
+        if next == MAX_CODES {
+            out.write(clear, size);
+            table.clear();
+            next = end + 1;
+            size = min_code_size + 1;
+        }
+
+        current = u16::from(pixel);
+    }
+
+    out.write(current, size);
+    out.write(end, size);
+    out.finish()
+}
+
+/// Animated GIF looping over `frames` (which all have the size of the first one), showing each
+/// for `delay` hundredths of a second
+pub fn gif(frames: &[IndexedImage], palette: &[Rgb], delay: u16) -> Vec<u8> {
+    assert!(
+        (1..=256).contains(&palette.len()),
+        "GIF palettes have 1 to 256 colours"
+    );
+
+    let bits = palette.len().next_power_of_two().trailing_zeros().max(1);
+    let (width, height) = frames.first().map_or((0, 0), |f| (f.width, f.height));
+    let [width, height] = [width, height].map(|side| {
+        u16::try_from(side)
+            .expect("GIF images are at most 65535 pixels wide")
+            .to_le_bytes()
+    });
+
+    let mut out = b"GIF89a".to_vec();
+    out.extend(width);
+    out.extend(height);
+    // Global colour table of 2^bits colours, background colour 0, square pixels
+    out.extend([0x80 | ((bits - 1) << 4) as u8 | (bits - 1) as u8, 0, 0]);
+    out.extend(palette.iter().flatten());
+    out.resize(out.len() + ((1 << bits) - palette.len()) * 3, 0);
+
+    // Loops forever
+    out.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    let min_code_size = bits.max(2);
+    for frame in frames {
+        out.extend([0x21, 0xf9, 0x04, 0x00]);
+        out.extend(delay.to_le_bytes());
+        out.extend([0x00, 0x00]);
+
+        out.push(0x2c);
+        out.extend([0, 0, 0, 0]);
+        out.extend(width);
+        out.extend(height);
+        out.push(0x00);
+
+        out.push(min_code_size as u8);
+        for block in lzw(min_code_size, &frame.pixels).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend(block);
+        }
+        out.push(0x00);
+    }
+
+    out.push(0x3b);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads back the pixels of `data`, following what GIF decoders do
+    fn unlzw(min_code_size: u32, data: &[u8]) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut size = min_code_size + 1;
+        let mut previous: Option<usize> = None;
+        let mut pixels = vec![];
+
+        let (mut buffer, mut len, mut bytes) = (0u32, 0, data.iter());
+        loop {
+            while len < size {
+                buffer |= u32::from(*bytes.next().expect("Missing end code")) << len;
+                len += 8;
+            }
+            let code = (buffer & ((1 << size) - 1)) as usize;
+            buffer >>= size;
+            len -= size;
+
+            if code == clear {
+                table = (0..clear).map(|p| vec![p as u8]).collect();
+                table.extend([vec![], vec![]]);
+                size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return pixels;
+            }
+
+            let entry = match (table.get(code), previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => {
+                    let mut entry = table[previous].clone();
+                    entry.push(entry[0]);
+                    entry
+                }
+                (None, None) => panic!("Unknown code {}", code),
+            };
+
+            if let Some(previous) = previous {
+                let mut added = table[previous].clone();
+                added.push(entry[0]);
+                table.push(added);
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+
+            pixels.extend(&entry);
+            previous = Some(code);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        let inputs = [
+            vec![],
+            vec![3],
+            vec![0; 10000],
+            (0..20000).map(|i| (i * i / 7 % 16) as u8).collect(),
+            b"ababababababababababab".iter().map(|b| b - b'a').collect(),
+        ];
+
+        for pixels in inputs {
+            assert_eq!(unlzw(4, &lzw(4, &pixels)), pixels);
+        }
+    }
+
+    #[test]
+    fn ppm_pixels() {
+        let image = IndexedImage::from_fn(2, 1, |_, col| col as u8).scaled(2);
+        assert_eq!(image.pixels, [0, 0, 1, 1, 0, 0, 1, 1]);
+
+        let palette = [[0, 0, 0], [255, 0, 10]];
+        let mut expected = b"P6\n4 2\n255\n".to_vec();
+        expected.extend([0, 0, 0, 0, 0, 0, 255, 0, 10, 255, 0, 10].repeat(2));
+        assert_eq!(ppm(&image, &palette), expected);
+    }
+
+    #[test]
+    fn gif_layout() {
+        let frames = [
+            IndexedImage::from_fn(3, 2, |row, col| (row + col) as u8),
+            IndexedImage::from_fn(3, 2, |_, _| 2),
+        ];
+        let palette = [[0, 0, 0], [1, 1, 1], [2, 2, 2]];
+        let data = gif(&frames, &palette, 50);
+
+        assert_eq!(&data[..6], b"GIF89a");
+        assert_eq!(&data[6..10], [3, 0, 2, 0]);
+        // 4 colours in the table, the last one is padding
+        assert_eq!(data[10], 0x80 | 0x10 | 0x01);
+        assert_eq!(&data[13..25], [0, 0, 0, 1, 1, 1, 2, 2, 2, 0, 0, 0]);
+        assert_eq!(data.last(), Some(&0x3b));
+
+        // Image data of the first frame, after its control extension and descriptor
+        let start = 25 + 19 + 8 + 10;
+        assert_eq!(data[start], 2);
+        let len = data[start + 1] as usize;
+        let pixels = unlzw(2, &data[(start + 2)..(start + 2 + len)]);
+        assert_eq!(pixels, frames[0].pixels);
+    }
+}
@@ -0,0 +1,190 @@
+use std::{error::Error, fmt, str::FromStr};
+
+/// Where something went wrong in a puzzle input, line and column start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub day: u32,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "day {}, line {}, column {}",
+            self.day, self.line, self.column
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// `text` could not be parsed as a number
+    InvalidNumber {
+        at: Location,
+        text: String,
+        reason: String,
+    },
+    /// `text` is not what the format allows at this point
+    Unexpected {
+        at: Location,
+        text: String,
+        expected: String,
+    },
+    /// `text` is well formed but describes something the puzzle does not allow
+    Invalid {
+        at: Location,
+        text: String,
+        reason: String,
+    },
+    /// The input ended before `expected` was found
+    Missing { day: u32, expected: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidNumber { at, text, reason } => {
+                write!(
+                    f,
+                    "{}: cannot parse {:?} as a number ({})",
+                    at, text, reason
+                )
+            }
+            ParseError::Unexpected { at, text, expected } => {
+                write!(f, "{}: unexpected {:?}, expected {}", at, text, expected)
+            }
+            ParseError::Invalid { at, text, reason } => {
+                write!(f, "{}: invalid {:?}, {}", at, text, reason)
+            }
+            ParseError::Missing { day, expected } => {
+                write!(f, "day {}: input ended before {}", day, expected)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl ParseError {
+    pub fn missing(day: u32, expected: impl Into<String>) -> Self {
+        Self::Missing {
+            day,
+            expected: expected.into(),
+        }
+    }
+}
+
+/// One line of a puzzle input, `text` is `raw` without the surrounding whitespace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLine<'a> {
+    pub day: u32,
+    pub number: usize,
+    pub raw: &'a str,
+    pub text: &'a str,
+}
+
+impl<'a> InputLine<'a> {
+    pub fn new(day: u32, number: usize, raw: &'a str) -> Self {
+        Self {
+            day,
+            number,
+            raw,
+            text: raw.trim(),
+        }
+    }
+
+    /// Location of `part`, which has to be a slice of this line (otherwise it points at column 1)
+    pub fn location(&self, part: &str) -> Location {
+        let offset = (part.as_ptr() as usize)
+            .checked_sub(self.raw.as_ptr() as usize)
+            .filter(|&offset| offset <= self.raw.len())
+            .unwrap_or_default();
+
+        self.location_at(self.raw[..offset].chars().count())
+    }
+
+    /// Location of the character at index `column` (starting at 0) of `raw`
+    pub fn location_at(&self, column: usize) -> Location {
+        Location {
+            day: self.day,
+            line: self.number,
+            column: column + 1,
+        }
+    }
+
+    pub fn parse<T>(&self, part: &str) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.parse_with(part, str::parse)
+    }
+
+    /// Same as `parse` with a custom number parser
+    pub fn parse_with<T, E: fmt::Display>(
+        &self,
+        part: &str,
+        parse: impl FnOnce(&str) -> Result<T, E>,
+    ) -> Result<T, ParseError> {
+        parse(part).map_err(|err| ParseError::InvalidNumber {
+            at: self.location(part),
+            text: part.to_owned(),
+            reason: err.to_string(),
+        })
+    }
+
+    pub fn unexpected(&self, part: &str, expected: impl Into<String>) -> ParseError {
+        ParseError::Unexpected {
+            at: self.location(part),
+            text: part.to_owned(),
+            expected: expected.into(),
+        }
+    }
+
+    pub fn invalid(&self, part: &str, reason: impl Into<String>) -> ParseError {
+        ParseError::Invalid {
+            at: self.location(part),
+            text: part.to_owned(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Every line of `input`, including the empty ones
+pub fn all_lines(day: u32, input: &str) -> impl Iterator<Item = InputLine<'_>> + Clone {
+    input
+        .lines()
+        .enumerate()
+        .map(move |(i, raw)| InputLine::new(day, i + 1, raw))
+}
+
+/// The non-empty lines of `input`
+pub fn lines(day: u32, input: &str) -> impl Iterator<Item = InputLine<'_>> + Clone {
+    all_lines(day, input).filter(|line| !line.text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations() {
+        let lines: Vec<_> = lines(3, "\n  12 ab\n\n x").collect();
+        assert_eq!(lines.len(), 2);
+
+        let err = lines[0]
+            .parse::<u32>(&lines[0].text[3..])
+            .expect_err("Parsed a letter");
+        assert_eq!(
+            err.to_string(),
+            "day 3, line 2, column 6: cannot parse \"ab\" as a number (invalid digit found in string)"
+        );
+
+        assert_eq!(
+            lines[1].unexpected(lines[1].text, "a digit").to_string(),
+            "day 3, line 4, column 2: unexpected \"x\", expected a digit"
+        );
+    }
+}
@@ -12,8 +12,13 @@ pub mod day06;
 pub mod day07;
 pub mod day08;
 pub mod day09;
-//pub mod day10;
-//pub mod day11;
-//pub mod day12;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+
+pub mod grid;
+pub mod image;
+pub mod input;
+pub mod ranges;
 
 aoc_lib! { year = 2025 }
@@ -0,0 +1,62 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// Integers inclusive ranges can be merged over
+pub trait Bound: Copy + Ord {
+    fn successor(self) -> Option<Self>;
+}
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {
+        $(
+            impl Bound for $t {
+                fn successor(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+            }
+        )*
+    };
+}
+
+impl_bound!(u32, u64, u128, usize);
+
+/// Merges overlapping and adjacent inclusive ranges, the result is sorted and disjoint
+pub fn merge_ranges<T: Bound>(ranges: &[(T, T)]) -> Vec<(T, T)> {
+    // BinaryHeap to the rescue
+    let mut heap: BinaryHeap<_> = ranges.iter().copied().map(Reverse).collect();
+    let mut result: Vec<(T, T)> = vec![];
+
+    while let Some(Reverse((lower, upper))) = heap.pop() {
+        let Some((_, last_upper)) = result.last_mut() else {
+            result.push((lower, upper));
+            continue;
+        };
+
+        if last_upper.successor().is_none_or(|next| lower <= next) {
+            *last_upper = upper.max(*last_upper);
+            continue;
+        }
+
+        result.push((lower, upper));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging() {
+        assert_eq!(
+            merge_ranges(&[(10u64, 14), (3, 5), (16, 20), (12, 18), (6, 6)]),
+            [(3, 6), (10, 20)]
+        );
+        assert_eq!(merge_ranges(&[(0u64, 0), (2, 3)]), [(0, 0), (2, 3)]);
+        assert_eq!(
+            merge_ranges(&[(5u128, u128::MAX), (0, 4), (7, 8)]),
+            [(0, u128::MAX)]
+        );
+        assert_eq!(merge_ranges::<u64>(&[]), []);
+    }
+}